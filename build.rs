@@ -0,0 +1,13 @@
+// Genera el servidor gRPC de `proto/movies.proto` (tonic + prost).
+// Usa `PROTOC` si está definido; si no, el `protoc` empaquetado en `protoc-bin-vendored`.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/movies.proto"], &["proto"])?;
+    println!("cargo:rerun-if-changed=proto/movies.proto");
+    Ok(())
+}
@@ -0,0 +1,8 @@
+// Las películas sin votos empiezan en 0 (el voto usa coalesce, pero /movie y los
+// filtros esperan un entero).
+MATCH (movie:Movie)
+WHERE movie.votes IS NULL
+CALL {
+  WITH movie
+  SET movie.votes = 0
+} IN TRANSACTIONS OF 1000 ROWS;
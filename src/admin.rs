@@ -0,0 +1,250 @@
+// ============================
+// API de administración (/admin/*)
+// ============================
+//
+// Autenticada con un token de portador (`Authorization: Bearer <ADMIN_TOKEN>`).
+// Sin `ADMIN_TOKEN` las rutas existen pero responden 403. Actúan sobre el tenant
+// de la petición, como el resto de la API.
+
+use std::sync::Arc;
+
+use async_compression::tokio::write::GzipEncoder;
+use axum::{
+    async_trait,
+    body::Body,
+    extract::{FromRequest, FromRequestParts, Multipart, Query, Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE},
+        request::Parts,
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use color_eyre::eyre::eyre;
+use futures::TryStreamExt as _;
+use serde::Deserialize;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::{error, info};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    IntoParams, Modify, ToSchema,
+};
+
+use crate::{
+    codec::{Accepts, Encoded},
+    dump,
+    import::{self, CsvKind, ImportReport},
+    logging::LogFilter,
+    tenant::Tenants,
+    AppError, Service,
+};
+
+/// Ajustes de la API de administración.
+#[derive(Clone, Default)]
+pub struct AdminConfig {
+    pub token: Option<Arc<str>>,
+    /// Filas por transacción de `/admin/import` (CSV y volcados)
+    pub import_batch_size: usize,
+    /// Máximo de filas de la consola Cypher (`POST /query`)
+    pub console_max_rows: usize,
+    /// Filtro de logs de `/admin/log-level` (sólo en el servidor, no en los subcomandos)
+    pub log_filter: Option<LogFilter>,
+}
+
+/// Petición autenticada como administrador. Se pide como extractor en los handlers.
+pub(crate) struct Admin;
+
+#[async_trait]
+impl FromRequestParts<Tenants> for Admin {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, tenants: &Tenants) -> Result<Self, Self::Rejection> {
+        let Some(expected) = tenants.admin.token.as_deref() else {
+            return Err(AppError::new(eyre!("admin API disabled (ADMIN_TOKEN not set)"), StatusCode::FORBIDDEN));
+        };
+        let given = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default()
+            .trim();
+        if !token_matches(given.as_bytes(), expected.as_bytes()) {
+            metrics::counter!("admin_auth_failures_total").increment(1);
+            return Err(AppError::new(eyre!("invalid admin token"), StatusCode::UNAUTHORIZED));
+        }
+        Ok(Admin)
+    }
+}
+
+/// Comparación en tiempo constante (para la misma longitud).
+fn token_matches(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Esquema `bearer` de la documentación OpenAPI.
+pub struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+// ============================
+// Handlers
+// ============================
+
+/// Partes del formulario de `/admin/import` (todas opcionales, al menos una).
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ImportUpload {
+    /// movies.csv: title, released, tagline
+    #[schema(value_type = Option<String>, format = Binary)]
+    movies: Option<Vec<u8>>,
+    /// people.csv: name, born
+    #[schema(value_type = Option<String>, format = Binary)]
+    people: Option<Vec<u8>>,
+    /// roles.csv: person, movie, type, roles, summary, rating
+    #[schema(value_type = Option<String>, format = Binary)]
+    roles: Option<Vec<u8>>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/import",
+    tag = "admin",
+    request_body(
+        content = ImportUpload,
+        content_type = "multipart/form-data",
+        description = "CSV de películas, personas y roles (se importan en el orden en que llegan, roles al final). \
+                       Con `Content-Type: application/x-ndjson` o `application/gzip`, el cuerpo es un volcado de \
+                       `/admin/export` que se restaura en una base de datos vacía"
+    ),
+    params(
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 200, description = "CSV: filas insertadas, actualizadas y rechazadas por fichero; volcado: nodos y relaciones restaurados", body = ImportReport),
+        (status = 400, description = "Formulario, parte o volcado inválido"),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada"),
+        (status = 409, description = "Restauración sobre una base de datos con datos"),
+        (status = 415, description = "Content-Type no admitido")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn import(
+    _admin: Admin,
+    Accepts(codec): Accepts,
+    State(tenants): State<Tenants>,
+    service: Service,
+    request: Request,
+) -> Result<Encoded<ImportReport>, AppError> {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let batch_size = tenants.admin.import_batch_size;
+
+    match content_type.as_str() {
+        "multipart/form-data" => {
+            let multipart = Multipart::from_request(request, &tenants).await.map_err(|e| bad_request(e.into()))?;
+            Ok(Encoded(codec, import_csv(&service, multipart, batch_size).await?))
+        }
+        "application/x-ndjson" | "application/jsonl" | "application/gzip" => {
+            let body = StreamReader::new(request.into_body().into_data_stream().map_err(std::io::Error::other));
+            let input = dump::decompressed(body).await?;
+            let report = dump::restore(&service.db, &service.tenant, input, batch_size).await?;
+            Ok(Encoded(codec, ImportReport { dump: Some(report), ..Default::default() }))
+        }
+        other => Err(AppError::new(
+            eyre!("unsupported content type `{other}` (multipart/form-data, application/x-ndjson or application/gzip)"),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        )),
+    }
+}
+
+fn bad_request(e: color_eyre::Report) -> AppError {
+    AppError::new(e, StatusCode::BAD_REQUEST)
+}
+
+async fn import_csv(service: &Service, mut multipart: Multipart, batch_size: usize) -> Result<ImportReport, AppError> {
+    let mut report = ImportReport::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| bad_request(e.into()))? {
+        let name = field.name().or(field.file_name()).unwrap_or_default().to_string();
+        let Some(kind) = CsvKind::from_name(&name).or_else(|| field.file_name().and_then(CsvKind::from_name)) else {
+            return Err(bad_request(eyre!("unknown part `{name}` (expected movies, people or roles)")));
+        };
+        let body = StreamReader::new(field.map_err(std::io::Error::other));
+        report.files.push(import::import_csv(&service.db, &service.tenant, kind, body, batch_size).await?);
+    }
+
+    if report.files.is_empty() {
+        return Err(bad_request(eyre!("no CSV parts (expected movies, people and/or roles)")));
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Comprime el volcado con gzip
+    #[serde(default)]
+    gzip: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/export",
+    tag = "admin",
+    params(
+        ExportQuery,
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 200, description = "Volcado JSON Lines del grafo completo, en streaming (termina con una línea `footer`; sin ella está truncado)",
+            content(("application/x-ndjson" = String), ("application/gzip" = String))),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada"),
+        (status = 503, description = "Neo4j no disponible")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn export(_admin: Admin, service: Service, Query(query): Query<ExportQuery>) -> Result<Response, AppError> {
+    // Los errores antes del primer byte aún pueden ser un 503; después sólo queda cortar
+    crate::warmup(&service.db).await.map_err(|e| AppError::new(e, StatusCode::SERVICE_UNAVAILABLE))?;
+
+    // El volcado se escribe en un tubo acotado: la memoria no depende del tamaño del grafo
+    let (content_type, extension) = if query.gzip { ("application/gzip", "jsonl.gz") } else { ("application/x-ndjson", "jsonl") };
+    let headers = [
+        (CONTENT_TYPE, content_type.to_string()),
+        (CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{extension}\"", service.tenant)),
+    ];
+
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    let gzip = query.gzip;
+    tokio::spawn(async move {
+        let res = if gzip {
+            dump::export(&service.db, &service.tenant, GzipEncoder::new(writer)).await
+        } else {
+            dump::export(&service.db, &service.tenant, writer).await
+        };
+        match res {
+            Ok(report) => info!(tenant = %service.tenant, ?report, "export streamed"),
+            Err(e) => error!(tenant = %service.tenant, error=?e, "export aborted; the dump is truncated"),
+        }
+    });
+
+    Ok((headers, Body::from_stream(ReaderStream::new(reader))).into_response())
+}
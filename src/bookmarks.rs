@@ -0,0 +1,101 @@
+// ============================
+// Bookmarks (read-your-writes)
+// ============================
+//
+// Las escrituras van al líder como transacciones WRITE y devuelven un bookmark de
+// Neo4j en la cabecera `x-neo4j-bookmark` y en la cookie `neo4j_bookmark`. Las
+// lecturas (transacciones READ, enrutadas a followers) aceptan ese bookmark por
+// cualquiera de las dos vías y esperan a que el follower lo haya alcanzado.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponseParts, ResponseParts},
+};
+use color_eyre::eyre::eyre;
+
+use crate::AppError;
+
+pub const BOOKMARK_HEADER: &str = "x-neo4j-bookmark";
+pub const BOOKMARK_COOKIE: &str = "neo4j_bookmark";
+
+const MAX_BOOKMARKS: usize = 16;
+const MAX_BOOKMARK_LEN: usize = 512;
+/// Vida de la cookie: basta con cubrir el retraso de replicación entre miembros
+const COOKIE_MAX_AGE_SECS: u32 = 300;
+
+/// Bookmarks que el cliente pide respetar en sus lecturas.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Bookmarks(Vec<String>);
+
+impl Bookmarks {
+    /// Formato que espera `Graph::start_txn_as` (`None` = sin restricción causal).
+    pub fn to_option(&self) -> Option<Vec<String>> {
+        (!self.0.is_empty()).then(|| self.0.clone())
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Result<Self, AppError> {
+        let mut bookmarks: Vec<String> = headers
+            .get_all(BOOKMARK_HEADER)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        // La cookie sólo se usa si no llega la cabecera explícita
+        if bookmarks.is_empty() {
+            bookmarks.extend(cookie_value(headers, BOOKMARK_COOKIE).map(str::to_string));
+        }
+
+        if bookmarks.len() > MAX_BOOKMARKS || bookmarks.iter().any(|b| b.len() > MAX_BOOKMARK_LEN) {
+            return Err(AppError::new(eyre!("invalid neo4j bookmark"), StatusCode::BAD_REQUEST));
+        }
+        Ok(Self(bookmarks))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Bookmarks {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_headers(&parts.headers)
+    }
+}
+
+/// Cabecera + cookie con el bookmark de la última escritura (si Neo4j devolvió uno).
+pub struct BookmarkResponse(pub Option<String>);
+
+impl IntoResponseParts for BookmarkResponse {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let Some(bookmark) = self.0 else { return Ok(res) };
+        if let Ok(value) = HeaderValue::from_str(&bookmark) {
+            res.headers_mut().insert(HeaderName::from_static(BOOKMARK_HEADER), value);
+        }
+        let cookie = format!(
+            "{BOOKMARK_COOKIE}={bookmark}; Path=/; Max-Age={COOKIE_MAX_AGE_SECS}; HttpOnly; SameSite=Lax"
+        );
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            res.headers_mut().append(header::SET_COOKIE, value);
+        }
+        Ok(res)
+    }
+}
+
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.trim())
+        .filter(|v| !v.is_empty())
+}
@@ -0,0 +1,388 @@
+// ============================
+// Circuit breaker (Neo4j)
+// ============================
+//
+// Closed ──(N fallos o llamadas lentas seguidas)──> Open ──(tras open_for)──> HalfOpen
+//   ^                                                                           │
+//   └─────────────────────(la sonda tiene éxito)────────────────────────────────┘
+//                                  (la sonda falla => vuelve a Open)
+//
+// Con el circuito abierto las llamadas fallan al instante con `CircuitOpen` (503)
+// en vez de esperar al `TimeoutLayer` y acumularse hasta `max_concurrency`.
+// Hay un breaker por tenant: una base de datos caída no corta el resto.
+
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{Report, Result};
+use neo4rs::Neo4jErrorKind;
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::retry;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Fallos (o llamadas lentas) consecutivos que abren el circuito
+    pub failure_threshold: u32,
+    /// Una llamada más lenta que esto cuenta como fallo
+    pub slow_call: Duration,
+    /// Tiempo en Open antes de dejar pasar una sonda (HalfOpen)
+    pub open_for: Duration,
+}
+
+/// Error devuelto sin tocar Neo4j mientras el circuito está abierto.
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitOpen;
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("circuit breaker open: neo4j calls are failing fast")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+
+    fn as_gauge(self) -> f64 {
+        match self {
+            BreakerState::Closed => 0.0,
+            BreakerState::Open => 1.0,
+            BreakerState::HalfOpen => 2.0,
+        }
+    }
+}
+
+/// Instantánea del breaker para `/readyz`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerSnapshot {
+    state: BreakerState,
+    consecutive_failures: u32,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    tenant: String,
+    cfg: BreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+enum Outcome {
+    Success,
+    Failure,
+    /// La llamada se canceló (o falló por causas ajenas a la BD) antes de ser lenta.
+    Neutral,
+}
+
+impl CircuitBreaker {
+    pub fn new(tenant: &str, cfg: BreakerConfig) -> Self {
+        metrics::gauge!("db_circuit_breaker_state", "tenant" => tenant.to_string()).set(BreakerState::Closed.as_gauge());
+        Self { tenant: tenant.to_string(), cfg, inner: Mutex::new(Inner::Closed { failures: 0 }) }
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let (state, consecutive_failures) = match *inner {
+            Inner::Closed { failures } => (BreakerState::Closed, failures),
+            Inner::Open { .. } => (BreakerState::Open, self.cfg.failure_threshold),
+            Inner::HalfOpen { .. } => (BreakerState::HalfOpen, self.cfg.failure_threshold),
+        };
+        BreakerSnapshot { state, consecutive_failures }
+    }
+
+    /// Ejecuta `fut` si el circuito lo permite y registra su resultado y latencia.
+    pub async fn call<T, F>(&self, operation: &'static str, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut permit = self.acquire(operation)?;
+        let res = fut.await;
+        let outcome = match &res {
+            Ok(_) if permit.started.elapsed() >= self.cfg.slow_call => Outcome::Failure,
+            Ok(_) => Outcome::Success,
+            Err(e) if is_db_failure(e) || permit.started.elapsed() >= self.cfg.slow_call => Outcome::Failure,
+            Err(_) => Outcome::Neutral,
+        };
+        permit.finish(outcome);
+        res
+    }
+
+    fn acquire(&self, operation: &'static str) -> Result<Permit<'_>, Report> {
+        let mut inner = self.inner.lock().unwrap();
+        let admitted = match *inner {
+            Inner::Closed { .. } => true,
+            Inner::Open { until } if Instant::now() >= until => {
+                self.transition(&mut inner, Inner::HalfOpen { probing: true });
+                true
+            }
+            Inner::Open { .. } => false,
+            Inner::HalfOpen { ref mut probing } if !*probing => {
+                *probing = true;
+                true
+            }
+            Inner::HalfOpen { .. } => false,
+        };
+        if !admitted {
+            metrics::counter!(
+                "db_circuit_breaker_rejected_total",
+                "tenant" => self.tenant.clone(),
+                "operation" => operation
+            )
+            .increment(1);
+            return Err(CircuitOpen.into());
+        }
+        Ok(Permit { breaker: self, started: Instant::now(), done: false })
+    }
+
+    fn record(&self, outcome: Outcome) {
+        let mut inner = self.inner.lock().unwrap();
+        let reopen = || Inner::Open { until: Instant::now() + self.cfg.open_for };
+        let next = match (&mut *inner, outcome) {
+            (Inner::Closed { failures }, Outcome::Success) => {
+                *failures = 0;
+                None
+            }
+            (Inner::Closed { failures }, Outcome::Failure) => {
+                *failures += 1;
+                (*failures >= self.cfg.failure_threshold).then(reopen)
+            }
+            (Inner::HalfOpen { .. }, Outcome::Success) => Some(Inner::Closed { failures: 0 }),
+            (Inner::HalfOpen { .. }, Outcome::Failure) => Some(reopen()),
+            (Inner::HalfOpen { probing }, Outcome::Neutral) => {
+                *probing = false;
+                None
+            }
+            // Llamadas que empezaron antes de abrir el circuito: no cambian nada
+            (Inner::Open { .. }, _) | (Inner::Closed { .. }, Outcome::Neutral) => None,
+        };
+        if let Some(next) = next {
+            self.transition(&mut inner, next);
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, next: Inner) {
+        let state = match next {
+            Inner::Closed { .. } => BreakerState::Closed,
+            Inner::Open { .. } => BreakerState::Open,
+            Inner::HalfOpen { .. } => BreakerState::HalfOpen,
+        };
+        match state {
+            BreakerState::Open => warn!(tenant = %self.tenant, open_for=?self.cfg.open_for, "circuit breaker opened"),
+            _ => info!(tenant = %self.tenant, state = state.as_str(), "circuit breaker state changed"),
+        }
+        metrics::gauge!("db_circuit_breaker_state", "tenant" => self.tenant.clone()).set(state.as_gauge());
+        metrics::counter!(
+            "db_circuit_breaker_transitions_total",
+            "tenant" => self.tenant.clone(),
+            "to" => state.as_str()
+        )
+        .increment(1);
+        *inner = next;
+    }
+}
+
+/// Permiso de una llamada en curso. Si la llamada se cancela (p.ej. salta el
+/// `TimeoutLayer`) se registra al soltarse: como fallo si ya era lenta.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    started: Instant,
+    done: bool,
+}
+
+impl Permit<'_> {
+    fn finish(&mut self, outcome: Outcome) {
+        self.done = true;
+        self.breaker.record(outcome);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let outcome = if self.started.elapsed() >= self.breaker.cfg.slow_call {
+                Outcome::Failure
+            } else {
+                Outcome::Neutral
+            };
+            self.breaker.record(outcome);
+        }
+    }
+}
+
+/// Cuenta como fallo de la BD: errores transitorios/de conexión y errores del servidor.
+/// Los errores de cliente (Cypher inválido, datos inexistentes…) no abren el circuito.
+fn is_db_failure(err: &Report) -> bool {
+    if retry::transient_reason(err).is_some() {
+        return true;
+    }
+    matches!(
+        err.downcast_ref::<neo4rs::Error>(),
+        Some(neo4rs::Error::Neo4j(e)) if matches!(e.kind(), Neo4jErrorKind::Database | Neo4jErrorKind::Unknown)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOW: Duration = Duration::from_millis(40);
+    const OPEN_FOR: Duration = Duration::from_millis(40);
+
+    fn breaker(failure_threshold: u32) -> CircuitBreaker {
+        CircuitBreaker::new("test", BreakerConfig { failure_threshold, slow_call: SLOW, open_for: OPEN_FOR })
+    }
+
+    fn state(b: &CircuitBreaker) -> BreakerState {
+        b.snapshot().state
+    }
+
+    async fn succeed(b: &CircuitBreaker) -> Result<()> {
+        b.call("test", async { Ok(()) }).await
+    }
+
+    async fn fail_db(b: &CircuitBreaker) -> Result<()> {
+        b.call("test", async { Err(neo4rs::Error::ConnectionError.into()) }).await
+    }
+
+    fn is_open_error(res: Result<()>) -> bool {
+        matches!(res, Err(e) if e.downcast_ref::<CircuitOpen>().is_some())
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_db_failures_and_fails_fast() {
+        let b = breaker(3);
+        for expected in 1..3 {
+            assert!(fail_db(&b).await.is_err());
+            assert_eq!(state(&b), BreakerState::Closed);
+            assert_eq!(b.snapshot().consecutive_failures, expected);
+        }
+        assert!(fail_db(&b).await.is_err());
+        assert_eq!(state(&b), BreakerState::Open);
+
+        let mut ran = false;
+        let res = b
+            .call("test", async {
+                ran = true;
+                Ok(())
+            })
+            .await;
+        assert!(is_open_error(res));
+        assert!(!ran, "con el circuito abierto no se toca la BD");
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_failure_count() {
+        let b = breaker(2);
+        assert!(fail_db(&b).await.is_err());
+        succeed(&b).await.unwrap();
+        assert_eq!(b.snapshot().consecutive_failures, 0);
+        assert!(fail_db(&b).await.is_err());
+        assert_eq!(state(&b), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn client_errors_do_not_count() {
+        let b = breaker(1);
+        let res: Result<()> = b.call("test", async { Err(color_eyre::eyre::eyre!("movie not found")) }).await;
+        assert!(res.is_err());
+        let res: Result<()> = b.call("test", async { Err(neo4rs::Error::NotSingleResult.into()) }).await;
+        assert!(res.is_err());
+        assert_eq!(state(&b), BreakerState::Closed);
+        assert_eq!(b.snapshot().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_closes_on_success() {
+        let b = breaker(1);
+        assert!(fail_db(&b).await.is_err());
+        assert_eq!(state(&b), BreakerState::Open);
+        tokio::time::sleep(OPEN_FOR).await;
+
+        // Sólo pasa una sonda a la vez
+        let mut probe = b.acquire("test").unwrap();
+        assert_eq!(state(&b), BreakerState::HalfOpen);
+        assert!(is_open_error(succeed(&b).await));
+        probe.finish(Outcome::Success);
+        drop(probe);
+
+        assert_eq!(state(&b), BreakerState::Closed);
+        succeed(&b).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_reopens_on_failure() {
+        let b = breaker(1);
+        assert!(fail_db(&b).await.is_err());
+        tokio::time::sleep(OPEN_FOR).await;
+        assert!(fail_db(&b).await.is_err());
+        assert_eq!(state(&b), BreakerState::Open);
+        assert!(is_open_error(succeed(&b).await));
+    }
+
+    #[tokio::test]
+    async fn slow_success_counts_as_failure() {
+        let b = breaker(2);
+        let slow = || {
+            b.call("test", async {
+                tokio::time::sleep(SLOW).await;
+                Ok(())
+            })
+        };
+        slow().await.unwrap();
+        assert_eq!(b.snapshot().consecutive_failures, 1);
+        slow().await.unwrap();
+        assert_eq!(state(&b), BreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn dropped_permit_counts_only_if_slow() {
+        let b = breaker(1);
+        // Cancelada antes de ser lenta: neutra
+        drop(b.acquire("test").unwrap());
+        assert_eq!(state(&b), BreakerState::Closed);
+        assert_eq!(b.snapshot().consecutive_failures, 0);
+
+        // Cancelada (p.ej. por el TimeoutLayer) después de ser lenta: fallo
+        let pending = b.call("test", std::future::pending::<Result<()>>());
+        assert!(tokio::time::timeout(SLOW * 2, pending).await.is_err());
+        assert_eq!(state(&b), BreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn dropped_probe_lets_the_next_one_through() {
+        let b = breaker(1);
+        assert!(fail_db(&b).await.is_err());
+        tokio::time::sleep(OPEN_FOR).await;
+        drop(b.acquire("test").unwrap());
+        assert_eq!(state(&b), BreakerState::HalfOpen);
+        succeed(&b).await.unwrap();
+        assert_eq!(state(&b), BreakerState::Closed);
+    }
+}
@@ -0,0 +1,323 @@
+// ============================
+// Línea de comandos
+// ============================
+//
+// Sin subcomando arranca el servidor HTTP. Los subcomandos son tareas puntuales de
+// operación contra la base de datos de cada tenant y terminan al acabar.
+
+use std::path::PathBuf;
+
+use async_compression::tokio::write::GzipEncoder;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use color_eyre::eyre::{bail, Result, WrapErr as _};
+use tokio::io::{AsyncWrite, BufWriter};
+
+use crate::{
+    dump,
+    import::{self, CsvKind, FileReport},
+    migrations::{self, Step},
+    schema::{self, SchemaDrift},
+    seed::{self, SeedOptions},
+    tenant::{self, TenantConfig},
+    AppConfig,
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Movies API (Axum + Neo4j)")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compara las restricciones e índices requeridos con los de la base de datos
+    Schema(SchemaArgs),
+    /// Migraciones de datos versionadas
+    Migrate(MigrateArgs),
+    /// Carga el dataset Movies embebido (idempotente)
+    Seed(SeedArgs),
+    /// Importa CSV de películas, personas y roles (inserta o actualiza) o restaura un volcado
+    Import(ImportArgs),
+    /// Vuelca el grafo completo a JSON Lines (opcionalmente gzip)
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Termina con error si falta alguna restricción o índice
+    #[arg(long, conflicts_with = "apply")]
+    check: bool,
+    /// Crea las restricciones e índices que falten
+    #[arg(long)]
+    apply: bool,
+    /// Sólo este tenant (por defecto, todos)
+    #[arg(long)]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    #[command(subcommand)]
+    action: MigrateAction,
+    /// Sólo este tenant (por defecto, todos)
+    #[arg(long, global = true)]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum MigrateAction {
+    /// Aplica las migraciones pendientes (con cerrojo entre réplicas)
+    Up {
+        /// Muestra lo que se aplicaría (con el Cypher) sin tocar la base de datos
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Lista las migraciones aplicadas y pendientes
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    /// Borra los datos antes de cargar (conserva el registro de migraciones)
+    #[arg(long)]
+    wipe: bool,
+    /// Copias sintéticas adicionales del grafo (títulos y nombres con sufijo ` #k`)
+    #[arg(long, default_value_t = 0)]
+    copies: u32,
+    /// Filas por transacción
+    #[arg(long, default_value_t = 1000)]
+    batch_size: usize,
+    /// Sólo este tenant (por defecto, todos)
+    #[arg(long)]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("files").required(true).multiple(true).args(["movies", "people", "roles", "dump"])))]
+pub struct ImportArgs {
+    /// movies.csv (title, released, tagline)
+    #[arg(long)]
+    movies: Option<PathBuf>,
+    /// people.csv (name, born)
+    #[arg(long)]
+    people: Option<PathBuf>,
+    /// roles.csv (person, movie, type, roles, summary, rating); se importa el último
+    #[arg(long)]
+    roles: Option<PathBuf>,
+    /// Volcado de `export` (.jsonl o .jsonl.gz) a restaurar en una base de datos vacía
+    #[arg(long, conflicts_with_all = ["movies", "people", "roles"])]
+    dump: Option<PathBuf>,
+    /// Filas por transacción
+    #[arg(long, default_value_t = 1000)]
+    batch_size: usize,
+    /// Tenant destino (por defecto, `DEFAULT_TENANT`)
+    #[arg(long)]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Fichero destino (por defecto, la salida estándar); con extensión `.gz` se comprime
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Comprime con gzip aunque el destino no acabe en `.gz`
+    #[arg(long)]
+    gzip: bool,
+    /// Tenant a volcar (por defecto, `DEFAULT_TENANT`)
+    #[arg(long)]
+    tenant: Option<String>,
+}
+
+pub async fn run(command: Command, cfg: &AppConfig) -> Result<()> {
+    match command {
+        Command::Schema(args) => schema(args, cfg).await,
+        Command::Migrate(args) => migrate(args, cfg).await,
+        Command::Seed(args) => seed(args, cfg).await,
+        Command::Import(args) => import(args, cfg).await,
+        Command::Export(args) => export(args, cfg).await,
+    }
+}
+
+/// Tenants sobre los que actúa un subcomando.
+fn selected_tenants(cfg: &AppConfig, only: Option<&str>) -> Result<Vec<TenantConfig>> {
+    let tenants = tenant::configs(cfg)?;
+    let Some(only) = only.map(str::to_ascii_lowercase) else { return Ok(tenants) };
+    let selected: Vec<TenantConfig> = tenants.into_iter().filter(|t| t.name == only).collect();
+    if selected.is_empty() {
+        bail!("unknown tenant `{only}`");
+    }
+    Ok(selected)
+}
+
+async fn schema(args: SchemaArgs, cfg: &AppConfig) -> Result<()> {
+    let mut drift = Vec::new();
+
+    for t in selected_tenants(cfg, args.tenant.as_deref())? {
+        let db = crate::db(&t)?;
+        let diff = schema::diff(&db).await?;
+
+        println!("tenant {} (database {})", t.name, t.database);
+        for item in &diff.present {
+            println!("  ok       {item}");
+        }
+        for item in &diff.missing {
+            println!("  missing  {item}");
+        }
+        for name in &diff.extra {
+            println!("  extra    {name} (not declared, left untouched)");
+        }
+
+        if args.apply {
+            schema::apply(&db, &diff).await?;
+            println!("  applied {} item(s)", diff.missing.len());
+        } else if diff.is_drifted() {
+            drift.push(SchemaDrift { tenant: t.name, missing: diff.missing.iter().map(|i| i.name).collect() });
+        }
+    }
+
+    match drift.into_iter().next() {
+        Some(drift) if args.check => Err(drift.into()),
+        _ => Ok(()),
+    }
+}
+
+async fn migrate(args: MigrateArgs, cfg: &AppConfig) -> Result<()> {
+    for t in selected_tenants(cfg, args.tenant.as_deref())? {
+        let db = crate::db(&t)?;
+        println!("tenant {} (database {})", t.name, t.database);
+
+        match args.action {
+            MigrateAction::Status => {
+                let applied = migrations::applied(&db).await?;
+                for m in migrations::MIGRATIONS {
+                    match applied.get(&m.version) {
+                        Some(a) => println!("  applied  {:04} {}  {}", m.version, m.name, a.applied_at),
+                        None => println!("  pending  {:04} {}", m.version, m.name),
+                    }
+                }
+                // Versiones registradas por un binario más nuevo que éste
+                let known = |v: &u32| migrations::MIGRATIONS.iter().any(|m| m.version == *v);
+                for (version, a) in applied.iter().filter(|(v, _)| !known(v)) {
+                    println!("  unknown  {version:04} {}  {} (not in this build)", a.name, a.applied_at);
+                }
+            }
+            MigrateAction::Up { dry_run: true } => {
+                let pending = migrations::pending(&migrations::applied(&db).await?);
+                if pending.is_empty() {
+                    println!("  up to date");
+                }
+                for m in pending {
+                    println!("  would apply {:04} {}", m.version, m.name);
+                    match m.step {
+                        Step::Cypher(cypher) => {
+                            for statement in migrations::statements(cypher)? {
+                                println!("{};", indent(statement, "      "));
+                            }
+                        }
+                        Step::Rust(_) => println!("      (Rust migration)"),
+                    }
+                }
+            }
+            MigrateAction::Up { dry_run: false } => {
+                let applied = migrations::up(&db, &t.name, cfg.migration_lock()).await?;
+                if applied.is_empty() {
+                    println!("  up to date");
+                }
+                for m in applied {
+                    println!("  applied  {:04} {}", m.version, m.name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn seed(args: SeedArgs, cfg: &AppConfig) -> Result<()> {
+    let opts = SeedOptions { wipe: args.wipe, copies: args.copies, batch_size: args.batch_size };
+    for t in selected_tenants(cfg, args.tenant.as_deref())? {
+        let db = crate::db(&t)?;
+        let report = seed::seed(&db, &t.name, opts).await?;
+        println!(
+            "tenant {} (database {}): {} movies, {} people, {} relationships",
+            t.name, t.database, report.movies, report.people, report.relationships
+        );
+    }
+    Ok(())
+}
+
+/// Tenant de los subcomandos que trabajan sobre uno solo: `--tenant` o el de por defecto.
+fn single_tenant(cfg: &AppConfig, only: Option<String>) -> Result<TenantConfig> {
+    let tenants = tenant::configs(cfg)?;
+    let only = only.or_else(|| cfg.default_tenant.clone()).unwrap_or_else(|| tenants[0].name.clone());
+    Ok(selected_tenants(cfg, Some(&only))?.remove(0))
+}
+
+async fn import(args: ImportArgs, cfg: &AppConfig) -> Result<()> {
+    // Un fichero de catálogo es de un tenant: sin `--tenant` va al de por defecto
+    let t = single_tenant(cfg, args.tenant)?;
+    let db = crate::db(&t)?;
+    println!("tenant {} (database {})", t.name, t.database);
+
+    if let Some(path) = args.dump {
+        let file = tokio::fs::File::open(&path).await.wrap_err_with(|| format!("cannot open {}", path.display()))?;
+        let report = dump::restore(&db, &t.name, dump::decompressed(file).await?, args.batch_size).await?;
+        println!("  {}: {} nodes, {} relationships restored", path.display(), report.nodes, report.relationships);
+        return Ok(());
+    }
+
+    let files = [(CsvKind::Movies, args.movies), (CsvKind::People, args.people), (CsvKind::Roles, args.roles)];
+    for (kind, path) in files {
+        let Some(path) = path else { continue };
+        let file = tokio::fs::File::open(&path).await.wrap_err_with(|| format!("cannot open {}", path.display()))?;
+        let report = import::import_csv(&db, &t.name, kind, file, args.batch_size).await?;
+        print_import(&path, &report);
+    }
+    Ok(())
+}
+
+async fn export(args: ExportArgs, cfg: &AppConfig) -> Result<()> {
+    let t = single_tenant(cfg, args.tenant)?;
+    let db = crate::db(&t)?;
+
+    let gzip = args.gzip || args.output.as_ref().is_some_and(|p| p.extension().is_some_and(|e| e == "gz"));
+    let out: Box<dyn AsyncWrite + Unpin + Send> = match &args.output {
+        Some(path) => Box::new(
+            tokio::fs::File::create(path).await.wrap_err_with(|| format!("cannot create {}", path.display()))?,
+        ),
+        None => Box::new(tokio::io::stdout()),
+    };
+    let out = BufWriter::new(out);
+    let report = if gzip {
+        dump::export(&db, &t.name, GzipEncoder::new(out)).await?
+    } else {
+        dump::export(&db, &t.name, out).await?
+    };
+    // Con la salida estándar ocupada por el volcado, el resumen va a stderr
+    eprintln!(
+        "tenant {} (database {}): {} nodes, {} relationships exported",
+        t.name, t.database, report.nodes, report.relationships
+    );
+    Ok(())
+}
+
+fn print_import(path: &std::path::Path, report: &FileReport) {
+    println!(
+        "  {}: {} inserted, {} updated, {} rejected",
+        path.display(),
+        report.inserted,
+        report.updated,
+        report.rejected_count
+    );
+    for row in &report.rejected {
+        println!("    line {}: {}", row.line, row.reason);
+    }
+    if report.rejected_count > report.rejected.len() as u64 {
+        println!("    … {} more", report.rejected_count - report.rejected.len() as u64);
+    }
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines().map(|l| format!("{prefix}{l}")).collect::<Vec<_>>().join("\n")
+}
@@ -0,0 +1,259 @@
+// ============================
+// Codificación de la API (JSON, MessagePack, CBOR)
+// ============================
+//
+// Los tipos de la API se serializan en JSON, MessagePack o CBOR según `Accept`
+// (`Accepts` + `Encoded`) y los cuerpos de petición se leen según `Content-Type`
+// (`Decoded`). Sin `Accept`, o con `*/*`, se responde JSON. Los errores (`AppError`)
+// siguen siendo siempre JSON.
+//
+// `CodecAddon` añade a la documentación OpenAPI las alternativas binarias de cada
+// contenido `application/json` (salvo los probes de salud y GraphQL, que sólo hablan JSON).
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use color_eyre::eyre::{eyre, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use utoipa::{openapi::RefOr, Modify};
+
+use crate::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Json,
+    MsgPack,
+    Cbor,
+}
+
+/// Codificación y tipos MIME que la identifican (el primero es el canónico).
+const CODECS: &[(Codec, &[&str])] = &[
+    (Codec::Json, &["application/json"]),
+    (Codec::MsgPack, &["application/msgpack", "application/x-msgpack", "application/vnd.msgpack"]),
+    (Codec::Cbor, &["application/cbor"]),
+];
+
+impl Codec {
+    pub fn media_type(self) -> &'static str {
+        CODECS.iter().find(|(codec, _)| *codec == self).map_or("application/json", |(_, mimes)| mimes[0])
+    }
+
+    pub fn from_media_type(media: &str) -> Option<Self> {
+        CODECS.iter().find(|(_, mimes)| mimes.contains(&media)).map(|(codec, _)| *codec)
+    }
+
+    /// Codificación preferida en `Accept`; `None` si no hay ninguna aceptable.
+    pub fn from_accept(headers: &HeaderMap) -> Option<Self> {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+            return Some(Self::Json);
+        };
+        best_match(accept, |media| match media {
+            "*/*" | "application/*" => Some(Self::Json),
+            media => Self::from_media_type(media),
+        })
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(value)?,
+            // Structs como mapas (con nombres de campo), igual que en JSON
+            Self::MsgPack => rmp_serde::to_vec_named(value)?,
+            Self::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out)?;
+                out
+            }
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        Ok(match self {
+            Self::Json => serde_json::from_slice(bytes)?,
+            Self::MsgPack => rmp_serde::from_slice(bytes)?,
+            Self::Cbor => ciborium::from_reader(bytes)?,
+        })
+    }
+}
+
+/// Recorre los rangos de `Accept` y devuelve la coincidencia de mayor `q`
+/// (a igualdad, la primera de la cabecera). `matcher` recibe el tipo en minúsculas.
+/// Una cabecera sin rangos utilizables (vacía o sólo con `q=0`) vale como `*/*`,
+/// igual que si no estuviera.
+pub fn best_match<T>(accept: &str, matcher: impl Fn(&str) -> Option<T>) -> Option<T> {
+    let mut best: Option<(f32, T)> = None;
+    let mut usable = false;
+    for range in accept.split(',') {
+        let mut parts = range.split(';').map(str::trim);
+        let media = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let q = parts.filter_map(|p| p.strip_prefix("q=")).find_map(|q| q.parse::<f32>().ok()).unwrap_or(1.0);
+        if media.is_empty() || q <= 0.0 {
+            continue;
+        }
+        usable = true;
+        match matcher(&media) {
+            Some(found) if best.as_ref().is_none_or(|(best_q, _)| q > *best_q) => best = Some((q, found)),
+            _ => {}
+        }
+    }
+    if !usable {
+        return matcher("*/*");
+    }
+    best.map(|(_, found)| found)
+}
+
+// ============================
+// Extractores y respuesta
+// ============================
+
+/// Codificación de la respuesta pedida en `Accept` (406 si no se admite ninguna).
+pub(crate) struct Accepts(pub Codec);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Accepts {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Codec::from_accept(&parts.headers).map(Accepts).ok_or_else(|| {
+            AppError::new(
+                eyre!("no acceptable encoding (application/json, application/msgpack or application/cbor)"),
+                StatusCode::NOT_ACCEPTABLE,
+            )
+        })
+    }
+}
+
+/// Valor de la API serializado con la codificación negociada.
+pub struct Encoded<T>(pub Codec, pub T);
+
+impl<T: Serialize> IntoResponse for Encoded<T> {
+    fn into_response(self) -> Response {
+        let Encoded(codec, value) = self;
+        match codec.encode(&value) {
+            Ok(body) => ([(header::CONTENT_TYPE, codec.media_type()), (header::VARY, "accept")], body).into_response(),
+            Err(e) => AppError::new(e, StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        }
+    }
+}
+
+/// Cuerpo de petición en JSON, MessagePack o CBOR según `Content-Type`.
+pub(crate) struct Decoded<T>(pub T);
+
+#[async_trait]
+impl<S: Send + Sync, T: DeserializeOwned> FromRequest<S> for Decoded<T> {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let media = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let Some(codec) = Codec::from_media_type(&media) else {
+            return Err(AppError::new(
+                eyre!("unsupported content type `{media}` (application/json, application/msgpack or application/cbor)"),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ));
+        };
+        let body = Bytes::from_request(request, state).await.map_err(|e| AppError::new(eyre!(e), StatusCode::BAD_REQUEST))?;
+        codec.decode(&body).map(Decoded).map_err(|e| AppError::new(e, StatusCode::BAD_REQUEST))
+    }
+}
+
+// ============================
+// OpenAPI
+// ============================
+
+/// Duplica cada contenido `application/json` como MessagePack y CBOR (mismo esquema).
+pub struct CodecAddon;
+
+/// Operaciones (por tag) que sólo hablan JSON.
+const JSON_ONLY_TAGS: &[&str] = &["health", "graphql"];
+
+impl Modify for CodecAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let operations = openapi.paths.paths.values_mut().flat_map(|item| item.operations.values_mut());
+        for operation in operations {
+            if operation.tags.iter().flatten().any(|tag| JSON_ONLY_TAGS.contains(&tag.as_str())) {
+                continue;
+            }
+            if let Some(body) = operation.request_body.as_mut() {
+                if let Some(json) = body.content.get(Codec::Json.media_type()).cloned() {
+                    body.content.insert(Codec::MsgPack.media_type().into(), json.clone());
+                    body.content.insert(Codec::Cbor.media_type().into(), json);
+                }
+            }
+            for response in operation.responses.responses.values_mut() {
+                let RefOr::T(response) = response else { continue };
+                if let Some(json) = response.content.get(Codec::Json.media_type()).cloned() {
+                    response.content.insert(Codec::MsgPack.media_type().into(), json.clone());
+                    response.content.insert(Codec::Cbor.media_type().into(), json);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: &str) -> Option<Codec> {
+        best_match(accept, Codec::from_media_type)
+    }
+
+    fn from_accept(accept: &str) -> Option<Codec> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, accept.parse().unwrap());
+        Codec::from_accept(&headers)
+    }
+
+    #[test]
+    fn highest_q_wins() {
+        assert_eq!(negotiate("application/json;q=0.5, application/cbor"), Some(Codec::Cbor));
+        assert_eq!(negotiate("application/cbor;q=0.2, application/msgpack;q=0.9"), Some(Codec::MsgPack));
+    }
+
+    #[test]
+    fn ties_keep_header_order() {
+        assert_eq!(negotiate("application/cbor, application/json"), Some(Codec::Cbor));
+        assert_eq!(negotiate("application/json;q=0.8, application/cbor;q=0.8"), Some(Codec::Json));
+    }
+
+    #[test]
+    fn q_zero_excludes_and_unknown_types_are_skipped() {
+        assert_eq!(negotiate("application/cbor;q=0, text/html, application/json;q=0.1"), Some(Codec::Json));
+        assert_eq!(negotiate("application/cbor;q=0, application/msgpack;q=0.5"), Some(Codec::MsgPack));
+        assert_eq!(negotiate("text/html, image/png"), None);
+        assert_eq!(from_accept("application/cbor;q=0, text/html"), None);
+    }
+
+    #[test]
+    fn header_without_usable_ranges_means_json() {
+        for accept in ["", "  ", " , ;q=1", "application/cbor;q=0", "application/cbor;q=0, application/json;q=0.0"] {
+            assert_eq!(from_accept(accept), Some(Codec::Json), "{accept:?}");
+        }
+    }
+
+    #[test]
+    fn parsing_is_lenient() {
+        // Mayúsculas, espacios, otros parámetros y `q` ilegible (vale 1)
+        let accept = " Application/CBOR ; charset=utf-8 ; q=0.7 ,application/json;q=0.6";
+        assert_eq!(negotiate(accept), Some(Codec::Cbor));
+        assert_eq!(negotiate("application/json;q=0.9, application/x-msgpack;q=abc"), Some(Codec::MsgPack));
+    }
+
+    #[test]
+    fn wildcards_and_missing_header_mean_json() {
+        assert_eq!(Codec::from_accept(&HeaderMap::new()), Some(Codec::Json));
+        assert_eq!(from_accept("*/*"), Some(Codec::Json));
+        assert_eq!(from_accept("application/*;q=0.5, application/cbor"), Some(Codec::Cbor));
+        assert_eq!(from_accept("text/html"), None);
+    }
+}
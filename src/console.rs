@@ -0,0 +1,559 @@
+// ============================
+// Consola Cypher de sólo lectura (POST /query)
+// ============================
+//
+// Consultas ad hoc para administradores, sin salir de la aplicación. Una consulta
+// sólo se ejecuta si:
+//   1. no usa cláusulas ni procedimientos con efectos fuera del grafo (`check`):
+//      `USE` (otra base de datos / tenant), `LOAD CSV`, `dbms.*`, `apoc.load.*`…;
+//   2. su `EXPLAIN` la clasifica como de lectura (tipo `r` del resumen de Neo4j);
+//   3. y además corre en una transacción READ, que el servidor no deja escribir.
+// Las filas se cortan en `limit` (como máximo `CONSOLE_MAX_ROWS`) y la consulta tiene
+// plazo `QUERY_TIMEOUT_CONSOLE_MS`. Si el resultado contiene nodos, relaciones o
+// caminos se devuelve como grafo (`BrowseResponse`); si no, como tabla.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{extract::State, http::StatusCode};
+use color_eyre::eyre::{eyre, Report, Result};
+use neo4rs::{BoltNode, BoltType, Neo4jErrorKind, Row};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    admin::Admin,
+    bookmarks::Bookmarks,
+    codec::{Accepts, Decoded, Encoded},
+    deadline::Cancellable,
+    dump,
+    tenant::Tenants,
+    AppError, BrowseResponse, Link, Node, Service,
+};
+
+/// Procedimientos que se pueden invocar con `CALL` (prefijos, en minúsculas): esquema,
+/// índices de texto/vectoriales y recorridos de APOC. `CALL { … }` (subconsulta) no cuenta.
+const ALLOWED_PROCEDURES: &[&str] = &[
+    "db.labels",
+    "db.relationshiptypes",
+    "db.propertykeys",
+    "db.schema.",
+    "db.index.fulltext.query",
+    "db.index.vector.query",
+    "apoc.meta.",
+    "apoc.path.",
+];
+
+/// Procedimientos de "lectura" con efectos fuera del grafo (ficheros, red, servidor):
+/// rechazados en cualquier posición, también como funciones.
+const DENIED_PROCEDURES: &[&str] =
+    &["dbms.", "apoc.load.", "apoc.import.", "apoc.export.", "apoc.systemdb.", "apoc.log.", "apoc.util.sleep"];
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConsoleQuery {
+    /// Consulta Cypher (una sola sentencia, de lectura)
+    query: String,
+    /// Parámetros (`$nombre` en la consulta)
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    params: serde_json::Map<String, serde_json::Value>,
+    /// Máximo de filas (por defecto y como mucho, `CONSOLE_MAX_ROWS`)
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ConsoleResult {
+    /// Valores escalares, listas y mapas (columnas en orden alfabético)
+    Table {
+        columns: Vec<String>,
+        #[schema(value_type = Vec<Vec<Object>>)]
+        rows: Vec<Vec<serde_json::Value>>,
+        /// Había más filas que `limit`
+        truncated: bool,
+    },
+    /// Nodos y relaciones devueltos (sueltos, en listas o en caminos), como `BrowseResponse`
+    Graph {
+        nodes: Vec<Node>,
+        links: Vec<Link>,
+        truncated: bool,
+    },
+}
+
+/// La consulta no es de sólo lectura o usa algo no permitido (400).
+#[derive(Debug)]
+pub struct Rejected(pub String);
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query rejected: {}", self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+// ============================
+// Handler
+// ============================
+
+#[utoipa::path(
+    post,
+    path = "/query",
+    tag = "admin",
+    request_body(content = ConsoleQuery, description = "Cypher de sólo lectura y sus parámetros"),
+    params(
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes)")
+    ),
+    responses(
+        (status = 200, description = "Tabla (`type: table`) o grafo (`type: graph`, como `/graph`)", body = ConsoleResult),
+        (status = 400, description = "Consulta inválida, con escrituras o con cláusulas/procedimientos no permitidos"),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada"),
+        (status = 504, description = "La consulta superó QUERY_TIMEOUT_CONSOLE_MS")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn query(
+    _admin: Admin,
+    Accepts(codec): Accepts,
+    State(tenants): State<Tenants>,
+    service: Service,
+    bookmarks: Bookmarks,
+    Decoded(request): Decoded<ConsoleQuery>,
+) -> Result<Encoded<ConsoleResult>, AppError> {
+    check(&request.query).map_err(|e| AppError::new(e.into(), StatusCode::BAD_REQUEST))?;
+    let max_rows = tenants.admin.console_max_rows;
+    let limit = request.limit.unwrap_or(max_rows).clamp(1, max_rows);
+    let result = service.console(request.query, request.params, limit, &bookmarks).await.map_err(|e| {
+        // Los errores de la consulta (sintaxis, tipos…) son del cliente
+        let client = matches!(
+            e.downcast_ref::<neo4rs::Error>(),
+            Some(neo4rs::Error::Neo4j(err)) if matches!(err.kind(), Neo4jErrorKind::Client(_))
+        );
+        if client || e.is::<Rejected>() {
+            AppError::new(e, StatusCode::BAD_REQUEST)
+        } else {
+            AppError::from(e)
+        }
+    })?;
+    Ok(Encoded(codec, result))
+}
+
+impl Service {
+    async fn console(
+        &self,
+        cypher: String,
+        params: serde_json::Map<String, serde_json::Value>,
+        limit: usize,
+        bookmarks: &Bookmarks,
+    ) -> Result<ConsoleResult> {
+        let cancel = Cancellable::new();
+        let explain = params
+            .iter()
+            .fold(neo4rs::query(&format!("EXPLAIN {cypher}")), |q, (k, v)| q.param(k, dump::bolt(v.clone())));
+        let q = params.into_iter().fold(cancel.query(&cypher), |q, (k, v)| q.param(&k, dump::bolt(v)));
+
+        let (rows, truncated) = self
+            .guarded_read("console", self.timeouts.console, &cancel, || async {
+                let mut txn = self.read_txn(bookmarks).await?;
+                let plan = self.execute(&mut txn, "console_explain", explain.clone()).await?;
+                let summary = plan.finish(&mut txn).await?;
+                if summary.query_type() != neo4rs::summary::Type::Read {
+                    return Err(Rejected(format!("not a read-only query ({:?})", summary.query_type())).into());
+                }
+
+                let mut stream = self.execute(&mut txn, "console", q.clone()).await?;
+                let mut rows = Vec::new();
+                while let Some(row) = stream.next(&mut txn).await? {
+                    if rows.len() == limit {
+                        stream.finish(&mut txn).await?;
+                        txn.rollback().await?;
+                        return Ok((rows, true));
+                    }
+                    rows.push(row);
+                }
+                txn.rollback().await?;
+                Ok((rows, false))
+            })
+            .await?;
+        result(&rows, truncated)
+    }
+}
+
+// ============================
+// Comprobación previa
+// ============================
+
+/// Rechaza varias sentencias, `USE`, `LOAD CSV`, los `CALL` a procedimientos fuera de
+/// `ALLOWED_PROCEDURES` y cualquier nombre de `DENIED_PROCEDURES` (también como función).
+/// Ignora literales y comentarios; lo demás lo decide el `EXPLAIN`.
+fn check(cypher: &str) -> Result<(), Rejected> {
+    let tokens = tokens(cypher)?;
+    let lower: Vec<String> = tokens.iter().map(|t| t.text().to_ascii_lowercase()).collect();
+    for (i, token) in lower.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| lower[p].as_str());
+        let next = lower.get(i + 1).map(String::as_str);
+        let next_is_name = matches!(tokens.get(i + 1), Some(Token::Name(_)));
+        // Palabra clave, no una variable, etiqueta o propiedad con ese nombre
+        let keyword = matches!(tokens[i], Token::Name(_)) && !matches!(prev, Some("(" | ":" | "$" | ","));
+        match token.as_str() {
+            ";" if lower[i + 1..].iter().any(|t| t != ";") => return Err(Rejected("only one statement is allowed".into())),
+            "use" if keyword && next_is_name => return Err(Rejected("USE (another database) is not allowed".into())),
+            "load" if next == Some("csv") => return Err(Rejected("LOAD CSV is not allowed".into())),
+            "call" if keyword && next_is_name => {
+                let name = next.unwrap_or_default();
+                if !ALLOWED_PROCEDURES.iter().any(|p| name.starts_with(p)) {
+                    return Err(Rejected(format!("procedure {name} is not allowed")));
+                }
+            }
+            name => {
+                if let Some(denied) = DENIED_PROCEDURES.iter().find(|p| name.starts_with(*p)) {
+                    return Err(Rejected(format!("procedure {denied}* is not allowed")));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Identificador, o nombre con puntos (`apoc.load.json`, `n.title`), sin comillas invertidas
+    Name(String),
+    Punct(char),
+}
+
+impl Token {
+    fn text(&self) -> std::borrow::Cow<'_, str> {
+        match self {
+            Self::Name(name) => name.into(),
+            Self::Punct(c) => c.to_string().into(),
+        }
+    }
+}
+
+/// Nombres y signos de puntuación, sin literales ni comentarios. Los identificadores entre
+/// comillas invertidas se desentrecomillan y se unen a sus vecinos por los puntos (aunque
+/// haya espacios o comentarios entre medias): ``CALL `apoc`.load . `json`()`` nombra
+/// `apoc.load.json`, igual que lo resuelve Neo4j.
+fn tokens(cypher: &str) -> Result<Vec<Token>, Rejected> {
+    let bytes = cypher.as_bytes();
+    let mut out: Vec<Token> = Vec::new();
+    // El último token es un nombre seguido de `.`: el siguiente nombre lo continúa
+    let mut dotted = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let name = match opaque(cypher, i).map_err(|e| Rejected(e.into()))? {
+            Some((Opaque::String, end)) => {
+                i = end;
+                dotted = false;
+                continue;
+            }
+            Some((Opaque::Comment, end)) => {
+                i = end;
+                continue;
+            }
+            Some((Opaque::QuotedName, end)) => {
+                // Dentro, una comilla invertida se escribe doblada
+                let name = cypher[i + 1..end - 1].replace("``", "`");
+                i = end;
+                name
+            }
+            None => match bytes[i] {
+                b'.' if matches!(out.last(), Some(Token::Name(_))) && !dotted => {
+                    dotted = true;
+                    i += 1;
+                    continue;
+                }
+                c if c.is_ascii_digit() => {
+                    // Números (`1.5`, `0x1F`, `1e3`): ni nombre ni puntuación
+                    while i < bytes.len()
+                        && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.')
+                    {
+                        i += 1;
+                    }
+                    dotted = false;
+                    continue;
+                }
+                c if c.is_ascii_punctuation() => {
+                    if dotted {
+                        out.push(Token::Punct('.'));
+                        dotted = false;
+                    }
+                    out.push(Token::Punct(c as char));
+                    i += 1;
+                    continue;
+                }
+                c if c.is_ascii_alphabetic() || c == b'_' => {
+                    let start = i;
+                    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                        i += 1;
+                    }
+                    cypher[start..i].to_string()
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            },
+        };
+        match out.last_mut() {
+            Some(Token::Name(prev)) if dotted => {
+                prev.push('.');
+                prev.push_str(&name);
+            }
+            _ => out.push(Token::Name(name)),
+        }
+        dotted = false;
+    }
+    if dotted {
+        out.push(Token::Punct('.'));
+    }
+    Ok(out)
+}
+
+/// Tramo de Cypher que no es código: su contenido no cuenta como palabras ni `;`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Opaque {
+    /// `'…'` o `"…"`, con escapes `\`
+    String,
+    /// `` `…` ``, con la comilla invertida doblada como escape
+    QuotedName,
+    /// `// …` hasta el fin de línea o `/* … */` (sin cerrar, hasta el final)
+    Comment,
+}
+
+/// Si en `i` empieza un literal, un nombre entre comillas invertidas o un comentario,
+/// su tipo y la posición justo después de su final. Lo comparten `check` y
+/// `migrations::statements`.
+pub(crate) fn opaque(cypher: &str, i: usize) -> Result<Option<(Opaque, usize)>, &'static str> {
+    let bytes = cypher.as_bytes();
+    Ok(Some(match bytes[i] {
+        quote @ (b'\'' | b'"') => {
+            let mut end = i + 1;
+            while end < bytes.len() && bytes[end] != quote {
+                end += if bytes[end] == b'\\' { 2 } else { 1 };
+            }
+            if end >= bytes.len() {
+                return Err("unterminated string");
+            }
+            (Opaque::String, end + 1)
+        }
+        b'`' => {
+            let mut end = i + 1;
+            loop {
+                let Some(close) = cypher[end..].find('`').map(|n| end + n) else {
+                    return Err("unterminated quoted identifier");
+                };
+                if bytes.get(close + 1) == Some(&b'`') {
+                    end = close + 2;
+                } else {
+                    break (Opaque::QuotedName, close + 1);
+                }
+            }
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'/') => {
+            (Opaque::Comment, cypher[i..].find('\n').map_or(bytes.len(), |n| i + n))
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            (Opaque::Comment, cypher[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2))
+        }
+        _ => return Ok(None),
+    }))
+}
+
+// ============================
+// Resultado: tabla o grafo
+// ============================
+
+fn result(rows: &[Row], truncated: bool) -> Result<ConsoleResult> {
+    let typed: Vec<HashMap<String, BoltType>> = rows.iter().map(|r| r.to()).collect::<Result<_, _>>()?;
+    if !typed.iter().flat_map(HashMap::values).any(has_graph) {
+        let mut columns: Vec<String> = typed.first().map(|r| r.keys().cloned().collect()).unwrap_or_default();
+        columns.sort();
+        let rows = rows
+            .iter()
+            .map(|row| columns.iter().map(|c| row.get::<serde_json::Value>(c)).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<_, _>>()?;
+        return Ok(ConsoleResult::Table { columns, rows, truncated });
+    }
+
+    let mut graph = GraphBuilder::default();
+    for value in typed.iter().flat_map(HashMap::values) {
+        graph.visit(value)?;
+    }
+    let BrowseResponse { nodes, links } = graph.finish();
+    Ok(ConsoleResult::Graph { nodes, links, truncated })
+}
+
+fn has_graph(value: &BoltType) -> bool {
+    match value {
+        BoltType::Node(_) | BoltType::Relation(_) | BoltType::Path(_) => true,
+        BoltType::List(list) => list.value.iter().any(has_graph),
+        BoltType::Map(map) => map.value.values().any(has_graph),
+        _ => false,
+    }
+}
+
+/// Nodos por id interno; relaciones por id, enlazadas al final (sus extremos pueden
+/// llegar en otra fila). Las relaciones cuyos extremos no se devolvieron se omiten.
+#[derive(Default)]
+struct GraphBuilder {
+    index: HashMap<i64, usize>,
+    nodes: Vec<Node>,
+    rels: HashSet<i64>,
+    pending: Vec<(i64, i64, String)>,
+}
+
+impl GraphBuilder {
+    fn visit(&mut self, value: &BoltType) -> Result<()> {
+        match value {
+            BoltType::Node(node) => {
+                self.node(node)?;
+            }
+            BoltType::Relation(rel) if self.rels.insert(rel.id.value) => {
+                self.pending.push((rel.start_node_id.value, rel.end_node_id.value, rel.typ.value.clone()));
+            }
+            BoltType::Path(path) => {
+                let nodes = path.nodes();
+                let rels = path.rels();
+                let mut prev = nodes.first().ok_or_else(|| eyre!("empty path"))?.id.value;
+                for node in &nodes {
+                    self.node(node)?;
+                }
+                // `indices`: pares (relación con signo, nodo) a partir del primer nodo
+                for pair in path.indices().chunks(2) {
+                    let [rel, next] = pair else { break };
+                    let rel_at = rel.value.unsigned_abs() as usize;
+                    let (Some(r), Some(n)) = (rels.get(rel_at.wrapping_sub(1)), nodes.get(next.value as usize)) else {
+                        return Err(Report::msg("malformed path"));
+                    };
+                    let next = n.id.value;
+                    if self.rels.insert(r.id.value) {
+                        let (s, t) = if rel.value > 0 { (prev, next) } else { (next, prev) };
+                        self.pending.push((s, t, r.typ.value.clone()));
+                    }
+                    prev = next;
+                }
+            }
+            BoltType::List(list) => {
+                for item in &list.value {
+                    self.visit(item)?;
+                }
+            }
+            BoltType::Map(map) => {
+                for item in map.value.values() {
+                    self.visit(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, node: &BoltNode) -> Result<()> {
+        let id = node.id.value;
+        if self.index.contains_key(&id) {
+            return Ok(());
+        }
+        let node = neo4rs::Node::new(node.clone());
+        let (_, label, title) = crate::extract_key_label_title(&node)?;
+        let props: serde_json::Value = node.to()?;
+        self.index.insert(id, self.nodes.len());
+        self.nodes.push(Node { title, label: label.to_string(), props });
+        Ok(())
+    }
+
+    fn finish(self) -> BrowseResponse {
+        let links = self
+            .pending
+            .into_iter()
+            .filter_map(|(s, t, rel)| Some(Link { source: *self.index.get(&s)?, target: *self.index.get(&t)?, rel }))
+            .collect();
+        BrowseResponse { nodes: self.nodes, links }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(s: &str) -> Token {
+        Token::Name(s.into())
+    }
+
+    fn rejected(cypher: &str) -> bool {
+        check(cypher).is_err()
+    }
+
+    #[test]
+    fn tokens_skip_literals_and_comments() {
+        let tokens = tokens("MATCH (m) // dbms.listConfig\n WHERE m.title = 'apoc.load.json' /* USE x */ RETURN m").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                name("MATCH"),
+                Token::Punct('('),
+                name("m"),
+                Token::Punct(')'),
+                name("WHERE"),
+                name("m.title"),
+                Token::Punct('='),
+                name("RETURN"),
+                name("m"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_unquote_and_join_dotted_names() {
+        assert_eq!(tokens("CALL `apoc.load.json`()").unwrap()[1], name("apoc.load.json"));
+        assert_eq!(tokens("CALL `dbms`.`listConfig`()").unwrap()[1], name("dbms.listConfig"));
+        assert_eq!(tokens("CALL apoc . /* x */ `load` .json()").unwrap()[1], name("apoc.load.json"));
+        assert_eq!(tokens("RETURN `a``b`").unwrap()[1], name("a`b"));
+        assert_eq!(tokens("RETURN 1.5, n.x").unwrap(), vec![name("RETURN"), Token::Punct(','), name("n.x")]);
+    }
+
+    #[test]
+    fn tokens_reject_unterminated_quotes() {
+        assert!(tokens("RETURN 'abc").is_err());
+        assert!(tokens("CALL `dbms.listConfig()").is_err());
+    }
+
+    #[test]
+    fn check_allows_plain_reads() {
+        assert!(check("MATCH (m:Movie) RETURN m.title LIMIT 10;").is_ok());
+        assert!(check("MATCH (use:Person {name: $use}) RETURN use.name, use").is_ok());
+        assert!(check("MATCH (n) RETURN n.load, n.call").is_ok());
+        assert!(check("MATCH (m) CALL { WITH m RETURN m.title AS t } RETURN t").is_ok());
+        assert!(check("CALL db.labels() YIELD label RETURN label").is_ok());
+        assert!(check("CALL `db`.`schema`.visualization()").is_ok());
+    }
+
+    #[test]
+    fn check_rejects_several_statements() {
+        assert!(rejected("MATCH (n) RETURN n; MATCH (m) RETURN m"));
+        assert!(!rejected("MATCH (n) RETURN n;;"));
+        assert!(!rejected("RETURN ';' AS semicolon"));
+    }
+
+    #[test]
+    fn check_rejects_use_and_load_csv() {
+        assert!(rejected("USE other MATCH (n) RETURN n"));
+        assert!(rejected("use `other-db` MATCH (n) RETURN n"));
+        assert!(rejected("LOAD CSV FROM 'file:///etc/passwd' AS line RETURN line"));
+        assert!(rejected("load /* x */ csv FROM 'http://x' AS l RETURN l"));
+    }
+
+    #[test]
+    fn check_rejects_denied_and_unknown_procedures() {
+        assert!(rejected("CALL dbms.listConfig()"));
+        assert!(rejected("CALL `apoc.load.json`('http://x')"));
+        assert!(rejected("CALL `dbms`.`listConfig`()"));
+        assert!(rejected("CALL apoc .load.json('http://x')"));
+        assert!(rejected("RETURN apoc.util.sleep(1000)"));
+        assert!(rejected("CALL custom.procedure()"));
+        assert!(!rejected("// CALL dbms.listConfig()\nRETURN 1"));
+    }
+}
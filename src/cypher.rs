@@ -0,0 +1,312 @@
+// ============================
+// Construcción de Cypher (filtros de /graph)
+// ============================
+//
+// `Cypher` compone cláusulas (MATCH, WITH, WHERE, RETURN…) y enlaza parámetros:
+// los valores de la petición nunca se interpolan en el texto. Un `WHERE` se une a la
+// cláusula anterior y sólo aparece si algún filtro añadió un predicado.
+//
+// `graph` traduce `Browse` a la consulta de `/graph` (y de `Browse` en gRPC). Un
+// filtro nuevo se añade en `GraphFilters` (lectura) y en `GraphFilters::apply`.
+
+use std::collections::BTreeMap;
+
+use neo4rs::BoltType;
+
+use crate::{deadline::Cancellable, Browse};
+
+/// Máximo de saltos con `root`.
+const MAX_DEPTH: u32 = 6;
+const DEFAULT_LIMIT: i32 = 200;
+
+#[derive(Debug, Default)]
+pub struct Cypher {
+    clauses: Vec<String>,
+    /// Predicados del `WHERE` de la última cláusula (se unen con `AND`)
+    predicates: Vec<String>,
+    params: BTreeMap<String, BoltType>,
+}
+
+impl Cypher {
+    /// Añade una cláusula (`MATCH`, `WITH DISTINCT`…) cerrando el `WHERE` pendiente.
+    pub fn clause(&mut self, keyword: &str, body: &str) -> &mut Self {
+        self.flush_where();
+        self.clauses.push(format!("{keyword} {body}"));
+        self
+    }
+
+    pub fn match_(&mut self, pattern: &str) -> &mut Self {
+        self.clause("MATCH", pattern)
+    }
+
+    /// Predicado del `WHERE` de la última cláusula.
+    pub fn and_where(&mut self, predicate: impl Into<String>) -> &mut Self {
+        self.predicates.push(predicate.into());
+        self
+    }
+
+    /// Enlaza `value` como `$name` y devuelve el marcador para usarlo en el texto.
+    pub fn param(&mut self, name: &str, value: impl Into<BoltType>) -> String {
+        self.params.insert(name.to_string(), value.into());
+        format!("${name}")
+    }
+
+    #[cfg(test)]
+    pub fn params(&self) -> &BTreeMap<String, BoltType> {
+        &self.params
+    }
+
+    /// Texto de la consulta, una cláusula por línea.
+    pub fn cypher(&mut self) -> String {
+        self.flush_where();
+        self.clauses.join("\n")
+    }
+
+    /// Consulta lista para ejecutar, con la marca de cancelación.
+    pub fn query(mut self, cancel: &Cancellable) -> neo4rs::Query {
+        let cypher = self.cypher();
+        self.params
+            .into_iter()
+            .fold(cancel.query(&cypher), |q, (name, value)| q.param(&name, value))
+    }
+
+    fn flush_where(&mut self) {
+        if !self.predicates.is_empty() {
+            let predicates = std::mem::take(&mut self.predicates);
+            self.clauses.push(format!("WHERE {}", predicates.join("\n  AND ")));
+        }
+    }
+}
+
+// ============================
+// /graph
+// ============================
+
+/// Consulta de `/graph` sin ejecutar.
+pub struct GraphCypher {
+    pub cypher: Cypher,
+    /// `root` + `depth`: caminos de longitud variable (otro timeout)
+    pub is_path: bool,
+}
+
+/// Construye el Cypher de `/graph` a partir de los filtros; `max_limit` depende del modo.
+pub fn graph(browse: &Browse, max_limit: i32) -> GraphCypher {
+    let filters = GraphFilters::from(browse);
+    let mut q = Cypher::default();
+
+    // Sin `depth` la raíz no se usa
+    let root = filters.root.clone().filter(|_| filters.depth >= 1);
+    let is_path = root.is_some();
+    let rel = if let Some(root) = root {
+        let root = q.param("root", root);
+        q.match_("(root)").and_where(format!(
+            "(root:Movie AND root.title = {root}) OR (root:Person AND root.name = {root}) OR (root:node {{title:{root}}})"
+        ));
+        // Los límites de una longitud variable no admiten parámetros: `depth` es un
+        // entero acotado a 1..=MAX_DEPTH, no texto de la petición
+        q.match_(&format!("p = (root)-[r*1..{}]-(n)", filters.depth))
+            .clause("UNWIND", "relationships(p) AS relx")
+            .clause("WITH DISTINCT", "startNode(relx) AS s, endNode(relx) AS t, type(relx) AS rel");
+        "rel"
+    } else {
+        q.match_("(s)-[r]->(t)");
+        "type(r)"
+    };
+
+    filters.apply(&mut q, rel);
+
+    let returned = if is_path { "rel" } else { "type(r) AS rel" };
+    q.clause("RETURN", &format!("s, t, {returned}, properties(s) AS sProps, properties(t) AS tProps"));
+    let limit = q.param("limit", browse.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, max_limit) as i64);
+    q.clause("LIMIT", &limit);
+
+    GraphCypher { cypher: q, is_path }
+}
+
+/// Filtros de `Browse` ya normalizados (CSV sin vacíos, relaciones en mayúsculas).
+struct GraphFilters {
+    rels: Vec<String>,
+    node_incl: Vec<String>,
+    node_excl: Vec<String>,
+    root: Option<String>,
+    depth: u32,
+    released_gte: Option<i64>,
+    released_lte: Option<i64>,
+}
+
+impl From<&Browse> for GraphFilters {
+    fn from(browse: &Browse) -> Self {
+        Self {
+            rels: csv(&browse.rel).map(|s| s.to_ascii_uppercase()).collect(),
+            node_incl: csv(&browse.node_incl).map(str::to_string).collect(),
+            node_excl: csv(&browse.node_excl).map(str::to_string).collect(),
+            root: browse.root.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string),
+            depth: browse.depth.unwrap_or(0).min(MAX_DEPTH),
+            released_gte: browse.released_gte,
+            released_lte: browse.released_lte,
+        }
+    }
+}
+
+impl GraphFilters {
+    /// Predicados sobre la relación (`rel`) y sus dos extremos (`s`, `t`); sólo los indicados.
+    fn apply(self, q: &mut Cypher, rel: &str) {
+        if !self.rels.is_empty() {
+            let rels = q.param("rels", self.rels);
+            q.and_where(format!("{rel} IN {rels}"));
+        }
+        if !self.node_incl.is_empty() {
+            let incl = q.param("node_incl", self.node_incl);
+            for n in ["s", "t"] {
+                q.and_where(format!("any(lbl IN labels({n}) WHERE lbl IN {incl})"));
+            }
+        }
+        if !self.node_excl.is_empty() {
+            let excl = q.param("node_excl", self.node_excl);
+            for n in ["s", "t"] {
+                q.and_where(format!("none(lbl IN labels({n}) WHERE lbl IN {excl})"));
+            }
+        }
+        // Los años sólo filtran películas; una sin `released` queda fuera
+        if let Some(year) = self.released_gte {
+            let year = q.param("released_gte", year);
+            for n in ["s", "t"] {
+                q.and_where(format!("(NOT {n}:Movie OR coalesce({n}.released, -1) >= {year})"));
+            }
+        }
+        if let Some(year) = self.released_lte {
+            let year = q.param("released_lte", year);
+            for n in ["s", "t"] {
+                q.and_where(format!("(NOT {n}:Movie OR coalesce({n}.released, 999999) <= {year})"));
+            }
+        }
+    }
+}
+
+fn csv(value: &Option<String>) -> impl Iterator<Item = &str> {
+    value.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browse() -> Browse {
+        Browse {
+            limit: None,
+            rel: None,
+            root: None,
+            depth: None,
+            node_incl: None,
+            node_excl: None,
+            released_gte: None,
+            released_lte: None,
+        }
+    }
+
+    fn build(browse: &Browse, max_limit: i32) -> (String, BTreeMap<String, BoltType>, bool) {
+        let GraphCypher { mut cypher, is_path } = graph(browse, max_limit);
+        (cypher.cypher(), cypher.params().clone(), is_path)
+    }
+
+    #[test]
+    fn without_filters_there_is_no_where() {
+        let (cypher, params, is_path) = build(&browse(), 1000);
+        assert_eq!(
+            cypher,
+            "MATCH (s)-[r]->(t)\n\
+             RETURN s, t, type(r) AS rel, properties(s) AS sProps, properties(t) AS tProps\n\
+             LIMIT $limit"
+        );
+        assert_eq!(params.keys().collect::<Vec<_>>(), ["limit"]);
+        assert_eq!(params["limit"], BoltType::from(200i64));
+        assert!(!is_path);
+    }
+
+    #[test]
+    fn only_set_filters_are_emitted() {
+        let b = Browse { rel: Some(" acted_in, ,DIRECTED".into()), released_gte: Some(1990), ..browse() };
+        let (cypher, params, _) = build(&b, 1000);
+        assert_eq!(
+            cypher,
+            "MATCH (s)-[r]->(t)\n\
+             WHERE type(r) IN $rels\n  \
+             AND (NOT s:Movie OR coalesce(s.released, -1) >= $released_gte)\n  \
+             AND (NOT t:Movie OR coalesce(t.released, -1) >= $released_gte)\n\
+             RETURN s, t, type(r) AS rel, properties(s) AS sProps, properties(t) AS tProps\n\
+             LIMIT $limit"
+        );
+        assert_eq!(params.keys().collect::<Vec<_>>(), ["limit", "released_gte", "rels"]);
+        assert_eq!(params["rels"], BoltType::from(vec!["ACTED_IN".to_string(), "DIRECTED".to_string()]));
+        assert_eq!(params["released_gte"], BoltType::from(1990i64));
+    }
+
+    #[test]
+    fn label_and_year_filters_apply_to_both_ends() {
+        let b = Browse {
+            node_incl: Some("Movie,Person".into()),
+            node_excl: Some("Secret".into()),
+            released_lte: Some(2000),
+            ..browse()
+        };
+        let (cypher, params, _) = build(&b, 1000);
+        for n in ["s", "t"] {
+            assert!(cypher.contains(&format!("any(lbl IN labels({n}) WHERE lbl IN $node_incl)")));
+            assert!(cypher.contains(&format!("none(lbl IN labels({n}) WHERE lbl IN $node_excl)")));
+            assert!(cypher.contains(&format!("(NOT {n}:Movie OR coalesce({n}.released, 999999) <= $released_lte)")));
+        }
+        assert_eq!(params.keys().collect::<Vec<_>>(), ["limit", "node_excl", "node_incl", "released_lte"]);
+    }
+
+    #[test]
+    fn root_with_depth_walks_paths() {
+        let b = Browse { root: Some(" The Matrix ".into()), depth: Some(9), rel: Some("ACTED_IN".into()), ..browse() };
+        let (cypher, params, is_path) = build(&b, 1000);
+        assert!(is_path);
+        assert_eq!(
+            cypher,
+            "MATCH (root)\n\
+             WHERE (root:Movie AND root.title = $root) OR (root:Person AND root.name = $root) OR (root:node {title:$root})\n\
+             MATCH p = (root)-[r*1..6]-(n)\n\
+             UNWIND relationships(p) AS relx\n\
+             WITH DISTINCT startNode(relx) AS s, endNode(relx) AS t, type(relx) AS rel\n\
+             WHERE rel IN $rels\n\
+             RETURN s, t, rel, properties(s) AS sProps, properties(t) AS tProps\n\
+             LIMIT $limit"
+        );
+        assert_eq!(params["root"], BoltType::from("The Matrix"));
+    }
+
+    #[test]
+    fn root_without_depth_is_ignored() {
+        let b = Browse { root: Some("The Matrix".into()), ..browse() };
+        let (cypher, params, is_path) = build(&b, 1000);
+        assert!(!is_path);
+        assert!(!cypher.contains("root"));
+        assert!(!params.contains_key("root"));
+    }
+
+    #[test]
+    fn values_are_bound_never_interpolated() {
+        let injection = "x' OR 1=1 //";
+        let b = Browse {
+            root: Some(injection.into()),
+            depth: Some(1),
+            rel: Some(injection.into()),
+            node_incl: Some(injection.into()),
+            ..browse()
+        };
+        let (cypher, _, _) = build(&b, 1000);
+        assert!(!cypher.contains("OR 1=1"));
+    }
+
+    #[test]
+    fn limit_is_clamped_to_the_mode() {
+        let (_, params, _) = build(&Browse { limit: Some(5000), ..browse() }, 1000);
+        assert_eq!(params["limit"], BoltType::from(1000i64));
+        let (_, params, _) = build(&Browse { limit: Some(5000), ..browse() }, 100_000);
+        assert_eq!(params["limit"], BoltType::from(5000i64));
+        let (_, params, _) = build(&Browse { limit: Some(0), ..browse() }, 1000);
+        assert_eq!(params["limit"], BoltType::from(1i64));
+    }
+}
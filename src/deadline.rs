@@ -0,0 +1,175 @@
+// ============================
+// Timeouts por consulta y cancelación
+// ============================
+//
+// Cada consulta lleva un comentario `/* qid:<uuid> */` en el Cypher. Si vence su
+// timeout, o si la petición HTTP se cancela (cliente desconectado, `TimeoutLayer`),
+// se busca la transacción con `SHOW TRANSACTIONS` y se termina en el servidor con
+// `TERMINATE TRANSACTIONS`, en vez de dejarla corriendo en Neo4j.
+//
+// Cada miembro de un clúster sólo ve (y termina) sus propias transacciones, así que la
+// búsqueda va por el mismo enrutado que la consulta: las lecturas, a un lector (y
+// también al líder, que las atiende si no hay followers); las escrituras, al líder.
+// Con varios followers el driver puede elegir para `SHOW TRANSACTIONS` uno distinto
+// del que corre la lectura: entonces no se encuentra y sólo la corta el
+// `db.transaction.timeout` del servidor (conviene tenerlo configurado).
+//
+// `neo4rs` no expone el `tx_timeout` del mensaje BEGIN de Bolt (su `BeginBuilder` lo
+// admite, pero `Graph::start_txn*` no lo pasa), así que el plazo se impone desde el
+// cliente y la cancelación se hace explícitamente.
+
+use std::{future::Future, time::Duration};
+
+use color_eyre::eyre::Result;
+use neo4rs::{DetachedRowStream, Graph, Operation};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Timeouts por tipo de operación.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryTimeouts {
+    pub search: Duration,
+    pub graph: Duration,
+    /// `/graph` con `root` + `depth` (caminos de longitud variable)
+    pub path: Duration,
+    /// Resto de operaciones (detalle de película, voto…)
+    pub default: Duration,
+    /// `/graph` en streaming: toda la respuesta, no sólo la consulta
+    pub stream: Duration,
+    /// Consola Cypher de administración (`POST /query`)
+    pub console: Duration,
+}
+
+/// La consulta superó su timeout y se canceló en el servidor.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryTimeout {
+    pub operation: &'static str,
+    pub after: Duration,
+}
+
+impl std::fmt::Display for QueryTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query `{}` timed out after {:?}", self.operation, self.after)
+    }
+}
+
+impl std::error::Error for QueryTimeout {}
+
+/// Consulta identificable en `SHOW TRANSACTIONS` para poder cancelarla.
+#[derive(Debug, Clone)]
+pub struct Cancellable {
+    tag: String,
+}
+
+impl Default for Cancellable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cancellable {
+    pub fn new() -> Self {
+        Self { tag: format!("qid:{}", Uuid::new_v4()) }
+    }
+
+    /// Construye la consulta con la marca en un comentario (no altera el plan).
+    pub fn query(&self, cypher: &str) -> neo4rs::Query {
+        neo4rs::query(&format!("/* {} */\n{}", self.tag, cypher))
+    }
+
+    /// Ejecuta `fut` con plazo `timeout`; si vence o se cancela, termina la transacción en Neo4j.
+    /// `access` es el modo de la transacción de `fut` (dónde buscarla para terminarla).
+    pub async fn run<T, F>(
+        &self,
+        db: &Graph,
+        tenant: &str,
+        operation: &'static str,
+        access: Operation,
+        timeout: Duration,
+        fut: F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut guard = CancelOnDrop { db, tenant, tag: &self.tag, access, armed: true };
+        let res = tokio::time::timeout(timeout, fut).await;
+        guard.armed = false;
+        match res {
+            Ok(res) => res,
+            Err(_) => {
+                metrics::counter!(
+                    "neo4j_query_timeouts_total",
+                    "tenant" => tenant.to_string(),
+                    "operation" => operation
+                )
+                .increment(1);
+                warn!(tenant, operation, ?timeout, tag = %self.tag, "query timed out, terminating transaction");
+                spawn_terminate(db.clone(), self.tag.clone(), access);
+                Err(QueryTimeout { operation, after: timeout }.into())
+            }
+        }
+    }
+}
+
+/// Si la petición se abandona a mitad (drop del future) cancela la transacción.
+struct CancelOnDrop<'a> {
+    db: &'a Graph,
+    tenant: &'a str,
+    tag: &'a str,
+    access: Operation,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            metrics::counter!("neo4j_query_cancellations_total", "tenant" => self.tenant.to_string()).increment(1);
+            debug!(tenant = %self.tenant, tag = %self.tag, "request dropped, terminating transaction");
+            spawn_terminate(self.db.clone(), self.tag.to_string(), self.access);
+        }
+    }
+}
+
+fn spawn_terminate(db: Graph, tag: String, access: Operation) {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(async move {
+            let members: &[Operation] = match access {
+                Operation::Read => &[Operation::Read, Operation::Write],
+                Operation::Write => &[Operation::Write],
+            };
+            for &member in members {
+                if let Err(e) = terminate(&db, &tag, member).await {
+                    warn!(error=?e, %tag, ?member, "failed to terminate neo4j transaction");
+                }
+            }
+        });
+    }
+}
+
+/// Termina las transacciones cuya consulta actual lleva la marca `tag` en el miembro al
+/// que se enrutan las transacciones `member`. La marca va como parámetro, así la propia
+/// consulta no se encuentra a sí misma.
+async fn terminate(db: &Graph, tag: &str, member: Operation) -> Result<()> {
+    const FIND: &str = r#"
+        SHOW TRANSACTIONS YIELD transactionId, currentQuery
+        WHERE currentQuery CONTAINS $tag
+        RETURN collect(transactionId) AS ids
+    "#;
+    const TERMINATE: &str = "TERMINATE TRANSACTIONS $ids";
+
+    let mut rows = routed(db, member, neo4rs::query(FIND).param("tag", tag)).await?;
+    let ids: Vec<String> = rows.single().await?.get("ids")?;
+    if ids.is_empty() {
+        return Ok(());
+    }
+    routed(db, member, neo4rs::query(TERMINATE).param("ids", ids.clone())).await?.finish().await?;
+    debug!(?ids, %tag, ?member, "neo4j transactions terminated");
+    Ok(())
+}
+
+async fn routed(db: &Graph, member: Operation, q: neo4rs::Query) -> Result<DetachedRowStream> {
+    Ok(match member {
+        Operation::Read => db.execute_read(q).await?,
+        Operation::Write => db.execute(q).await?,
+    })
+}
@@ -0,0 +1,511 @@
+// ============================
+// Volcado y restauración del grafo (JSON Lines)
+// ============================
+//
+// Formato portable para mover datos entre entornos sin `neo4j-admin`: una línea
+// JSON por registro, opcionalmente comprimido con gzip.
+//
+//   {"type":"header","format":"movies-graph-dump","version":1,"tenant":"movies"}
+//   {"type":"node","id":"4:…:0","labels":["Movie"],"properties":{"title":"The Matrix",…}}
+//   {"type":"relationship","id":"5:…:0","rel_type":"ACTED_IN","start":"4:…:1","end":"4:…:0","properties":{…}}
+//   {"type":"footer","nodes":171,"relationships":253}
+//
+// Los nodos van antes que las relaciones y el pie lleva los totales: un volcado sin
+// pie está truncado. Los ids son `elementId` del origen y sólo sirven para enlazar.
+// Las propiedades que JSON no representa (fechas, duraciones, puntos) se escriben
+// como `{"$type":"datetime","value":"…"}` y se reconstruyen con la función Cypher
+// del mismo nombre. La contabilidad de migraciones (`:__Migration*`) y las vistas
+// guardadas (`:__SavedView`) no se vuelcan: son del entorno, no de los datos.
+//
+// Ambos sentidos trabajan en streaming: la memoria depende del lote, no del grafo.
+
+use std::{collections::HashMap, fmt};
+
+use async_compression::tokio::bufread::GzipDecoder;
+use color_eyre::eyre::{bail, Result, WrapErr as _};
+use neo4rs::{BoltType, Graph, Operation};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt as _, AsyncRead, AsyncWrite, AsyncWriteExt as _, BufReader};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::schema::{self, SchemaMode};
+
+pub const FORMAT: &str = "movies-graph-dump";
+pub const VERSION: u32 = 1;
+
+/// Etiquetas que no se vuelcan ni cuentan para "base de datos vacía".
+pub const INTERNAL_LABELS: &[&str] = &["__Migration", "__MigrationLock", crate::views::VIEW_LABEL];
+
+/// Marca temporal de los nodos restaurados, para enlazar las relaciones por id de origen.
+const RESTORE_LABEL: &str = "__DumpNode";
+
+/// Tipos de `valueType()` que JSON representa tal cual.
+pub const NATIVE_TYPES: &str = r"^(STRING|INTEGER|FLOAT|BOOLEAN|LIST<(NOTHING|(STRING|INTEGER|FLOAT|BOOLEAN)( \| (STRING|INTEGER|FLOAT|BOOLEAN))* NOT NULL)>) NOT NULL$";
+
+/// Tipo de `valueType()` → función Cypher que lo reconstruye desde su `toString()`.
+const TYPED: &[(&str, &str)] = &[
+    ("DATE NOT NULL", "date"),
+    ("ZONED DATETIME NOT NULL", "datetime"),
+    ("LOCAL DATETIME NOT NULL", "localdatetime"),
+    ("ZONED TIME NOT NULL", "time"),
+    ("LOCAL TIME NOT NULL", "localtime"),
+    ("DURATION NOT NULL", "duration"),
+    // Los puntos se vuelcan como mapa {x, y[, z], srid}
+    ("POINT NOT NULL", "point"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    Header {
+        format: String,
+        version: u32,
+        #[serde(default)]
+        tenant: Option<String>,
+    },
+    Node {
+        id: String,
+        labels: Vec<String>,
+        properties: Map<String, Value>,
+    },
+    Relationship {
+        id: String,
+        rel_type: String,
+        start: String,
+        end: String,
+        properties: Map<String, Value>,
+    },
+    Footer {
+        nodes: u64,
+        relationships: u64,
+    },
+}
+
+/// Totales de un volcado o de una restauración.
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+pub struct DumpReport {
+    pub nodes: u64,
+    pub relationships: u64,
+}
+
+/// La base de datos destino ya tiene datos (la restauración sólo va a una vacía).
+#[derive(Debug, Clone)]
+pub struct NotEmpty {
+    pub tenant: String,
+}
+
+impl fmt::Display for NotEmpty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "database of tenant `{}` is not empty; restore only into an empty database", self.tenant)
+    }
+}
+
+impl std::error::Error for NotEmpty {}
+
+/// El volcado no es válido (cabecera, línea o pie).
+#[derive(Debug, Clone)]
+pub struct InvalidDump(pub String);
+
+impl fmt::Display for InvalidDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid dump: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDump {}
+
+// ============================
+// Volcado
+// ============================
+
+/// Propiedades de `{var}`: pares nativos y `[clave, tipo, valor]` para el resto.
+fn props_projection(var: &str) -> String {
+    format!(
+        r#"[k IN keys({var}) WHERE valueType({var}[k]) =~ $native | [k, {var}[k]]] AS plain,
+           [k IN keys({var}) WHERE NOT valueType({var}[k]) =~ $native |
+              [k, valueType({var}[k]),
+               CASE
+                 WHEN valueType({var}[k]) = 'POINT NOT NULL' AND {var}[k].z IS NULL
+                   THEN {{x: {var}[k].x, y: {var}[k].y, srid: {var}[k].srid}}
+                 WHEN valueType({var}[k]) = 'POINT NOT NULL'
+                   THEN {{x: {var}[k].x, y: {var}[k].y, z: {var}[k].z, srid: {var}[k].srid}}
+                 WHEN valueType({var}[k]) IN $typed THEN toString({var}[k])
+               END]] AS typed"#
+    )
+}
+
+/// Escribe el grafo completo en `out` (JSON Lines) y lo cierra.
+pub async fn export<W>(db: &Graph, tenant: &str, mut out: W) -> Result<DumpReport>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let nodes_query = format!(
+        "MATCH (n) WHERE none(l IN labels(n) WHERE l IN $internal)
+         RETURN elementId(n) AS id, labels(n) AS labels, {}",
+        props_projection("n")
+    );
+    let relationships_query = format!(
+        "MATCH (a)-[r]->(b)
+         WHERE none(l IN labels(a) WHERE l IN $internal) AND none(l IN labels(b) WHERE l IN $internal)
+         RETURN elementId(r) AS id, type(r) AS rel_type, elementId(a) AS start, elementId(b) AS end, {}",
+        props_projection("r")
+    );
+    let query = |q: &str| {
+        neo4rs::query(q)
+            .param("internal", INTERNAL_LABELS.to_vec())
+            .param("native", NATIVE_TYPES)
+            .param("typed", TYPED.iter().map(|(t, _)| *t).collect::<Vec<_>>())
+    };
+
+    let mut line = Vec::new();
+    let mut report = DumpReport::default();
+    write_record(
+        &mut out,
+        &mut line,
+        &Record::Header { format: FORMAT.into(), version: VERSION, tenant: Some(tenant.into()) },
+    )
+    .await?;
+
+    // Una sola transacción de lectura: nodos y relaciones salen de la misma instantánea
+    let mut txn = db.start_txn_as(Operation::Read, None).await?;
+    let mut rows = txn.execute(query(&nodes_query)).await?;
+    while let Some(row) = rows.next(txn.handle()).await? {
+        let id: String = row.get("id")?;
+        let properties = properties(row.get("plain")?, row.get("typed")?).wrap_err_with(|| format!("node {id}"))?;
+        write_record(&mut out, &mut line, &Record::Node { labels: row.get("labels")?, id, properties }).await?;
+        report.nodes += 1;
+    }
+
+    let mut rows = txn.execute(query(&relationships_query)).await?;
+    while let Some(row) = rows.next(txn.handle()).await? {
+        let id: String = row.get("id")?;
+        let properties =
+            properties(row.get("plain")?, row.get("typed")?).wrap_err_with(|| format!("relationship {id}"))?;
+        let record = Record::Relationship {
+            rel_type: row.get("rel_type")?,
+            start: row.get("start")?,
+            end: row.get("end")?,
+            id,
+            properties,
+        };
+        write_record(&mut out, &mut line, &record).await?;
+        report.relationships += 1;
+    }
+    txn.commit().await?;
+
+    write_record(
+        &mut out,
+        &mut line,
+        &Record::Footer { nodes: report.nodes, relationships: report.relationships },
+    )
+    .await?;
+    out.shutdown().await?;
+    info!(tenant, nodes = report.nodes, relationships = report.relationships, "graph exported");
+    Ok(report)
+}
+
+async fn write_record<W: AsyncWrite + Unpin>(out: &mut W, line: &mut Vec<u8>, record: &Record) -> Result<()> {
+    line.clear();
+    serde_json::to_writer(&mut *line, record)?;
+    line.push(b'\n');
+    out.write_all(line).await?;
+    Ok(())
+}
+
+/// Une los pares nativos y los tipados en el mapa de propiedades del volcado.
+fn properties(plain: Vec<Vec<Value>>, typed: Vec<Vec<Value>>) -> Result<Map<String, Value>> {
+    let mut properties = Map::new();
+    for pair in plain {
+        if let [Value::String(key), value] = pair.as_slice() {
+            properties.insert(key.clone(), value.clone());
+        }
+    }
+    for entry in typed {
+        let [Value::String(key), Value::String(value_type), value] = entry.as_slice() else { continue };
+        let Some((_, function)) = TYPED.iter().find(|(t, _)| t == value_type) else {
+            bail!("property `{key}` has unsupported type {value_type}");
+        };
+        properties.insert(key.clone(), serde_json::json!({ "$type": function, "value": value }));
+    }
+    Ok(properties)
+}
+
+// ============================
+// Restauración
+// ============================
+
+/// Entrada del volcado, descomprimida si empieza por la firma de gzip.
+pub async fn decompressed<R>(input: R) -> Result<Box<dyn AsyncBufRead + Unpin + Send>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let mut input = BufReader::new(input);
+    let gzip = input.fill_buf().await?.starts_with(&[0x1f, 0x8b]);
+    Ok(if gzip {
+        let mut decoder = GzipDecoder::new(input);
+        decoder.multiple_members(true);
+        Box::new(BufReader::new(decoder))
+    } else {
+        Box::new(input)
+    })
+}
+
+/// Restaura un volcado en una base de datos vacía, en lotes de `batch_size` filas.
+pub async fn restore<R>(db: &Graph, tenant: &str, input: R, batch_size: usize) -> Result<DumpReport>
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    const NOT_EMPTY: &str = r#"
+        RETURN EXISTS { MATCH (n) WHERE none(l IN labels(n) WHERE l IN $internal) } AS not_empty
+    "#;
+    let mut rows = db.execute(neo4rs::query(NOT_EMPTY).param("internal", INTERNAL_LABELS.to_vec())).await?;
+    if rows.single().await?.get::<bool>("not_empty")? {
+        return Err(NotEmpty { tenant: tenant.to_string() }.into());
+    }
+
+    let mut lines = input.lines();
+    let first = lines.next_line().await.wrap_err("cannot read dump")?;
+    match first.as_deref().map(serde_json::from_str::<Record>) {
+        Some(Ok(Record::Header { format, version, .. })) if format == FORMAT && version == VERSION => {}
+        Some(Ok(Record::Header { format, version, .. })) => {
+            return Err(InvalidDump(format!("unsupported format {format} v{version}")).into())
+        }
+        _ => return Err(InvalidDump("missing header line".into()).into()),
+    }
+
+    schema::sync(db, tenant, SchemaMode::Apply).await?;
+    let restore_index = format!(
+        "CREATE INDEX dump_node_id IF NOT EXISTS FOR (n:{RESTORE_LABEL}) ON (n.__dump_id)"
+    );
+    db.run(neo4rs::query(&restore_index)).await?;
+    db.run(neo4rs::query("CALL db.awaitIndexes(300)")).await?;
+
+    let res = restore_records(db, &mut lines, batch_size.max(1)).await;
+    let report = match res {
+        Ok(report) => {
+            if let Err(e) = cleanup(db).await {
+                warn!(tenant, error=?e, "failed to remove restore markers");
+            }
+            report
+        }
+        Err(e) => {
+            // Fallo a medias: se borra lo restaurado (lleva la marca) para que la base
+            // vuelva a estar vacía y el reintento no choque con `NotEmpty`
+            if let Err(undo) = undo_restore(db).await {
+                error!(tenant, error=?undo, "failed to undo a partial restore; delete the :{RESTORE_LABEL} nodes before retrying");
+            }
+            return Err(e);
+        }
+    };
+    info!(tenant, nodes = report.nodes, relationships = report.relationships, "graph restored");
+    Ok(report)
+}
+
+async fn restore_records<R>(db: &Graph, lines: &mut tokio::io::Lines<R>, batch_size: usize) -> Result<DumpReport>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut report = DumpReport::default();
+    let mut batches = Batches::new(batch_size);
+    let mut line_no = 1_u64;
+    let mut nodes_done = false;
+
+    while let Some(line) = lines.next_line().await.wrap_err("cannot read dump")? {
+        line_no += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(&line)
+            .map_err(|e| InvalidDump(format!("line {line_no}: {e}")))?;
+
+        match record {
+            Record::Node { id, labels, properties } => {
+                if nodes_done {
+                    return Err(InvalidDump(format!("line {line_no}: node after relationships")).into());
+                }
+                let (query, row) = node_row(id, &labels, properties)
+                    .map_err(|e| InvalidDump(format!("line {line_no}: {e}")))?;
+                report.nodes += batches.push(db, query, row).await?;
+            }
+            Record::Relationship { rel_type, start, end, properties, .. } => {
+                if !nodes_done {
+                    // Todas las relaciones necesitan sus nodos ya creados
+                    report.nodes += batches.flush_all(db).await?;
+                    nodes_done = true;
+                }
+                let (query, row) = relationship_row(&rel_type, start, end, properties)
+                    .map_err(|e| InvalidDump(format!("line {line_no}: {e}")))?;
+                report.relationships += batches.push(db, query, row).await?;
+            }
+            Record::Footer { nodes, relationships } => {
+                let created = batches.flush_all(db).await?;
+                if nodes_done {
+                    report.relationships += created;
+                } else {
+                    report.nodes += created;
+                }
+                if (nodes, relationships) != (report.nodes, report.relationships) {
+                    return Err(InvalidDump(format!(
+                        "footer says {nodes} nodes / {relationships} relationships, restored {} / {} \
+                         (relationships whose nodes are missing are skipped)",
+                        report.nodes, report.relationships
+                    ))
+                    .into());
+                }
+                return Ok(report);
+            }
+            Record::Header { .. } => return Err(InvalidDump(format!("line {line_no}: repeated header")).into()),
+        }
+    }
+    Err(InvalidDump("missing footer line: the dump is truncated".into()).into())
+}
+
+/// Lotes por consulta (cada combinación de etiquetas/tipo y propiedades tipadas
+/// genera su propia sentencia).
+struct Batches {
+    size: usize,
+    pending: HashMap<String, Vec<BoltType>>,
+}
+
+impl Batches {
+    fn new(size: usize) -> Self {
+        Self { size, pending: HashMap::new() }
+    }
+
+    /// Añade una fila; si el lote se llena, lo escribe. Devuelve lo creado.
+    async fn push(&mut self, db: &Graph, query: String, row: BoltType) -> Result<u64> {
+        let rows = self.pending.entry(query.clone()).or_default();
+        rows.push(row);
+        if rows.len() < self.size {
+            return Ok(0);
+        }
+        let rows = self.pending.remove(&query).unwrap_or_default();
+        write_batch(db, &query, rows).await
+    }
+
+    async fn flush_all(&mut self, db: &Graph) -> Result<u64> {
+        let mut created = 0;
+        for (query, rows) in std::mem::take(&mut self.pending) {
+            created += write_batch(db, &query, rows).await?;
+        }
+        Ok(created)
+    }
+}
+
+/// Un lote por transacción explícita: `Graph::execute` reintenta por su cuenta y un
+/// reintento tras un commit cuya respuesta se perdió duplicaría los `CREATE`.
+async fn write_batch(db: &Graph, query: &str, rows: Vec<BoltType>) -> Result<u64> {
+    let mut txn = db.start_txn().await?;
+    let mut result = txn.execute(neo4rs::query(query).param("rows", rows)).await?;
+    let created: i64 = result.single(txn.handle()).await?.get("created")?;
+    txn.commit().await?;
+    Ok(created as u64)
+}
+
+fn node_row(id: String, labels: &[String], properties: Map<String, Value>) -> Result<(String, BoltType)> {
+    let (plain, typed, set_typed) = split_properties(properties, "n")?;
+    let labels: String = labels.iter().map(|l| format!(":{}", quote(l))).collect();
+    let query = format!(
+        "UNWIND $rows AS row
+         CREATE (n{labels}:{RESTORE_LABEL} {{__dump_id: row.id}})
+         SET n += row.props{set_typed}
+         RETURN count(n) AS created"
+    );
+    let row = HashMap::from([("id", BoltType::from(id)), ("props", plain), ("typed", typed)]);
+    Ok((query, row.into()))
+}
+
+fn relationship_row(rel_type: &str, start: String, end: String, properties: Map<String, Value>) -> Result<(String, BoltType)> {
+    let (plain, typed, set_typed) = split_properties(properties, "r")?;
+    let query = format!(
+        "UNWIND $rows AS row
+         MATCH (a:{RESTORE_LABEL} {{__dump_id: row.start}})
+         MATCH (b:{RESTORE_LABEL} {{__dump_id: row.end}})
+         CREATE (a)-[r:{}]->(b)
+         SET r += row.props{set_typed}
+         RETURN count(r) AS created",
+        quote(rel_type)
+    );
+    let row = HashMap::from([
+        ("start", BoltType::from(start)),
+        ("end", end.into()),
+        ("props", plain),
+        ("typed", typed),
+    ]);
+    Ok((query, row.into()))
+}
+
+/// Separa las propiedades nativas (mapa para `SET +=`) de las tipadas, que se
+/// reconstruyen con su función en `SET` propios (las claves no pueden ser parámetros).
+fn split_properties(properties: Map<String, Value>, var: &str) -> Result<(BoltType, BoltType, String)> {
+    let mut plain: HashMap<String, BoltType> = HashMap::new();
+    let mut typed: HashMap<String, BoltType> = HashMap::new();
+    let mut typed_keys: Vec<(String, &'static str)> = Vec::new();
+
+    for (key, value) in properties {
+        match value {
+            Value::Object(obj) => {
+                let function = obj.get("$type").and_then(Value::as_str).unwrap_or_default();
+                let Some((_, function)) = TYPED.iter().find(|(_, f)| *f == function) else {
+                    bail!("property `{key}` has unknown $type `{function}`");
+                };
+                typed.insert(key.clone(), bolt(obj.get("value").cloned().unwrap_or(Value::Null)));
+                typed_keys.push((key, function));
+            }
+            value => {
+                plain.insert(key, bolt(value));
+            }
+        }
+    }
+
+    typed_keys.sort();
+    let set_typed: String = typed_keys
+        .iter()
+        .map(|(key, function)| format!(", {var}.{key} = {function}(row.typed.{key})", key = quote(key)))
+        .collect();
+    Ok((plain.into(), typed.into(), set_typed))
+}
+
+/// JSON → valor Bolt (la feature `json` de neo4rs no está activada).
+pub(crate) fn bolt(value: Value) -> BoltType {
+    match value {
+        Value::Null => BoltType::Null(neo4rs::BoltNull),
+        Value::Bool(b) => b.into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.into(),
+        Value::Array(items) => items.into_iter().map(bolt).collect::<Vec<_>>().into(),
+        Value::Object(obj) => obj.into_iter().map(|(k, v)| (k, bolt(v))).collect::<HashMap<_, _>>().into(),
+    }
+}
+
+/// Identificador Cypher entre comillas invertidas (etiquetas, tipos, claves).
+fn quote(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Borra lo restaurado hasta ahora (nodos con la marca y sus relaciones) y el índice.
+async fn undo_restore(db: &Graph) -> Result<()> {
+    let delete = format!(
+        "MATCH (n:{RESTORE_LABEL})
+         CALL {{ WITH n DETACH DELETE n }} IN TRANSACTIONS OF 1000 ROWS"
+    );
+    db.run(neo4rs::query(&delete)).await?;
+    db.run(neo4rs::query("DROP INDEX dump_node_id IF EXISTS")).await?;
+    Ok(())
+}
+
+/// Quita la marca de restauración y su índice.
+async fn cleanup(db: &Graph) -> Result<()> {
+    let remove = format!(
+        "MATCH (n:{RESTORE_LABEL})
+         CALL {{ WITH n REMOVE n:{RESTORE_LABEL}, n.__dump_id }} IN TRANSACTIONS OF 10000 ROWS"
+    );
+    db.run(neo4rs::query(&remove)).await?;
+    db.run(neo4rs::query("DROP INDEX dump_node_id IF EXISTS")).await?;
+    Ok(())
+}
@@ -0,0 +1,424 @@
+// ============================
+// Formatos de salida de /graph
+// ============================
+//
+// `/graph` responde por defecto el JSON propio del frontend D3 (`BrowseResponse`,
+// enlaces por índice), o ese mismo valor en MessagePack o CBOR (`codec.rs`). Con
+// `format=` o la cabecera `Accept` devuelve también GraphML, GEXF (Gephi) o JSON de
+// Cytoscape.js, o una tabla Arrow (`table.rs`); `format=` tiene prioridad sobre `Accept`.
+//
+// En los formatos de exportación cada nodo lleva `label` (título visible), `kind`
+// (`movie`, `person`, `node`) y todas sus propiedades (`Node.props`); cada arista, su
+// tipo de relación (`rel`). Una propiedad que choque con esos nombres sale como
+// `prop_<nombre>`.
+//
+// `application/x-ndjson` (`format=ndjson`) es el modo streaming: un registro JSON por
+// línea (`node`, `link` y un `end` final) que se emite según llegan las filas de Bolt,
+// con `limit` hasta `STREAM_MAX_LIMIT` (ver `Service::graph_stream`).
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use utoipa::IntoParams;
+
+use crate::{
+    codec::{self, Codec, Encoded},
+    table::{self, Table},
+    AppError, BrowseResponse, Link, Node,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GraphFormat {
+    Json,
+    GraphMl,
+    Gexf,
+    Cytoscape,
+    Ndjson,
+    MsgPack,
+    Cbor,
+    Arrow,
+}
+
+impl From<Codec> for GraphFormat {
+    fn from(codec: Codec) -> Self {
+        match codec {
+            Codec::Json => Self::Json,
+            Codec::MsgPack => Self::MsgPack,
+            Codec::Cbor => Self::Cbor,
+        }
+    }
+}
+
+/// Formato, valor de `format=` y tipo MIME.
+const FORMATS: &[(GraphFormat, &str, &str)] = &[
+    (GraphFormat::Json, "json", "application/json"),
+    (GraphFormat::GraphMl, "graphml", "application/graphml+xml"),
+    (GraphFormat::Gexf, "gexf", "application/gexf+xml"),
+    (GraphFormat::Cytoscape, "cytoscape", "application/vnd.cytoscape.js+json"),
+    (GraphFormat::Ndjson, "ndjson", "application/x-ndjson"),
+    (GraphFormat::MsgPack, "msgpack", "application/msgpack"),
+    (GraphFormat::Cbor, "cbor", "application/cbor"),
+    (GraphFormat::Arrow, "arrow", table::ARROW_STREAM),
+];
+
+/// `limit` máximo de `/graph` en streaming (el resto de formatos se queda en 1000).
+pub const STREAM_MAX_LIMIT: i32 = 100_000;
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// Formato de salida: json (por defecto), msgpack, cbor, graphml, gexf, cytoscape, arrow o ndjson (streaming). Tiene prioridad sobre `Accept`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+impl GraphFormat {
+    /// Codificación de los formatos que son un `BrowseResponse` tal cual.
+    pub fn codec(self) -> Option<Codec> {
+        match self {
+            Self::Json => Some(Codec::Json),
+            Self::MsgPack => Some(Codec::MsgPack),
+            Self::Cbor => Some(Codec::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Elige el formato a partir de `format=` o, si no viene, de `Accept`.
+    pub fn negotiate(format: Option<&str>, headers: &HeaderMap) -> Result<Self, AppError> {
+        if let Some(name) = format.map(str::trim).filter(|s| !s.is_empty()) {
+            let name = name.to_ascii_lowercase();
+            return FORMATS
+                .iter()
+                .find(|(_, param, _)| *param == name)
+                .map(|(format, _, _)| *format)
+                .ok_or_else(|| AppError::new(eyre!("unknown graph format `{name}`"), StatusCode::BAD_REQUEST));
+        }
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+            return Ok(Self::Json);
+        };
+        accepted(accept).ok_or_else(|| {
+            AppError::new(eyre!("no acceptable graph format for `{accept}`"), StatusCode::NOT_ACCEPTABLE)
+        })
+    }
+
+    pub fn media_type(self) -> &'static str {
+        FORMATS.iter().find(|(format, _, _)| *format == self).map_or("application/json", |(_, _, mime)| mime)
+    }
+
+    /// `table` sólo cuenta en Arrow (una tabla por respuesta: nodos o aristas).
+    pub fn render(self, graph: &BrowseResponse, table: Table) -> Response {
+        let headers = [(header::CONTENT_TYPE, self.media_type()), (header::VARY, "accept")];
+        let body = match self {
+            Self::Json => return (headers, Json(graph)).into_response(),
+            Self::Cytoscape => return (headers, Json(cytoscape(graph))).into_response(),
+            Self::GraphMl => graphml(graph),
+            Self::Gexf => gexf(graph),
+            Self::Ndjson => Ok(ndjson(graph)),
+            // Mismo `BrowseResponse` que en JSON, en binario
+            Self::MsgPack => return Encoded(Codec::MsgPack, graph).into_response(),
+            Self::Cbor => return Encoded(Codec::Cbor, graph).into_response(),
+            Self::Arrow => return table::render(graph, table),
+        };
+        (headers, body.expect("writing to a String cannot fail")).into_response()
+    }
+}
+
+/// `format=` + `Accept` como extractor de los handlers.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for GraphFormat {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<FormatQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::new(eyre!("{e}"), StatusCode::BAD_REQUEST))?;
+        Self::negotiate(query.format.as_deref(), &parts.headers)
+    }
+}
+
+/// Formato preferido según `Accept` (mayor `q`; a igualdad, el primero de la cabecera).
+fn accepted(accept: &str) -> Option<GraphFormat> {
+    codec::best_match(accept, |media| match media {
+        "*/*" | "application/*" => Some(GraphFormat::Json),
+        "application/xml" | "text/xml" => Some(GraphFormat::GraphMl),
+        media => FORMATS
+            .iter()
+            .find(|(_, _, mime)| *mime == media)
+            .map(|(format, _, _)| *format)
+            .or_else(|| Codec::from_media_type(media).map(GraphFormat::from)),
+    })
+}
+
+// ============================
+// Streaming (NDJSON)
+// ============================
+
+/// Una línea de `application/x-ndjson`. Los nodos se numeran en orden de aparición y
+/// los enlaces los referencian por ese `id` (como los índices de `BrowseResponse`).
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GraphRecord<'a> {
+    Node {
+        id: usize,
+        #[serde(flatten)]
+        node: &'a Node,
+    },
+    Link(&'a Link),
+    /// Último registro: sin él, la respuesta se cortó a medias
+    End { nodes: usize, links: usize },
+}
+
+impl GraphRecord<'_> {
+    /// Añade el registro y su salto de línea a `out`.
+    pub fn write_line(&self, out: &mut Vec<u8>) {
+        serde_json::to_writer(&mut *out, self).expect("graph records always serialize");
+        out.push(b'\n');
+    }
+}
+
+fn ndjson(graph: &BrowseResponse) -> String {
+    let mut out = Vec::new();
+    for (id, node) in graph.nodes.iter().enumerate() {
+        GraphRecord::Node { id, node }.write_line(&mut out);
+    }
+    for link in &graph.links {
+        GraphRecord::Link(link).write_line(&mut out);
+    }
+    GraphRecord::End { nodes: graph.nodes.len(), links: graph.links.len() }.write_line(&mut out);
+    String::from_utf8(out).expect("serde_json writes UTF-8")
+}
+
+// ============================
+// Atributos
+// ============================
+
+/// Nombres que usan los propios formatos; una propiedad así se renombra.
+const RESERVED: &[&str] = &["id", "label", "kind", "source", "target", "rel"];
+
+pub(crate) fn prop_name(key: &str) -> Cow<'_, str> {
+    if RESERVED.contains(&key) {
+        Cow::Owned(format!("prop_{key}"))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Tipo de un atributo (mismos nombres en GraphML y GEXF; también las columnas Arrow).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttrType {
+    Long,
+    Double,
+    Boolean,
+    String,
+}
+
+impl AttrType {
+    pub(crate) fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Bool(_) => Some(Self::Boolean),
+            Value::Number(n) if n.is_i64() || n.is_u64() => Some(Self::Long),
+            Value::Number(_) => Some(Self::Double),
+            // Listas y mapas se exportan como su JSON
+            _ => Some(Self::String),
+        }
+    }
+
+    /// Tipo de un `valueType()` de Cypher (`INTEGER NOT NULL`…); listas y el resto, texto.
+    pub(crate) fn of_value_type(value_type: &str) -> Self {
+        match value_type.trim_end_matches(" NOT NULL") {
+            "INTEGER" => Self::Long,
+            "FLOAT" => Self::Double,
+            "BOOLEAN" => Self::Boolean,
+            _ => Self::String,
+        }
+    }
+
+    /// Tipo común de una columna con valores de tipos distintos.
+    pub(crate) fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::Long, Self::Double) | (Self::Double, Self::Long) => Self::Double,
+            _ => Self::String,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Long => "long",
+            Self::Double => "double",
+            Self::Boolean => "boolean",
+            Self::String => "string",
+        }
+    }
+}
+
+/// Propiedades de nodo presentes en el grafo (nombre exportado → tipo).
+pub(crate) fn node_columns(graph: &BrowseResponse) -> BTreeMap<String, AttrType> {
+    let mut columns: BTreeMap<String, AttrType> = BTreeMap::new();
+    for (key, value) in graph.nodes.iter().flat_map(|n| props(&n.props)) {
+        if let Some(ty) = AttrType::of(value) {
+            columns.entry(prop_name(key).into_owned()).and_modify(|t| *t = t.merge(ty)).or_insert(ty);
+        }
+    }
+    columns
+}
+
+pub(crate) fn props(props: &Value) -> impl Iterator<Item = (&str, &Value)> {
+    props.as_object().into_iter().flatten().map(|(k, v)| (k.as_str(), v)).filter(|(_, v)| !v.is_null())
+}
+
+pub(crate) fn text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        other => Cow::Owned(other.to_string()),
+    }
+}
+
+/// Escapa texto para XML (y descarta los caracteres de control que XML 1.0 no admite).
+fn xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// ============================
+// Serializadores
+// ============================
+
+fn graphml(graph: &BrowseResponse) -> Result<String, fmt::Error> {
+    let columns = node_columns(graph);
+    let ids: BTreeMap<&str, String> = columns.keys().enumerate().map(|(i, name)| (name.as_str(), format!("p{i}"))).collect();
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    writeln!(out, r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#)?;
+    writeln!(out, r#"  <key id="kind" for="node" attr.name="kind" attr.type="string"/>"#)?;
+    for (name, ty) in &columns {
+        writeln!(out, r#"  <key id="{}" for="node" attr.name="{}" attr.type="{}"/>"#, ids[name.as_str()], xml(name), ty.name())?;
+    }
+    writeln!(out, r#"  <key id="rel" for="edge" attr.name="rel" attr.type="string"/>"#)?;
+    writeln!(out, r#"  <graph id="movies" edgedefault="directed">"#)?;
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        write!(out, r#"    <node id="n{i}"><data key="label">{}</data><data key="kind">{}</data>"#, xml(&node.title), xml(&node.label))?;
+        for (key, value) in props(&node.props) {
+            write!(out, r#"<data key="{}">{}</data>"#, ids[&*prop_name(key)], xml(&text(value)))?;
+        }
+        writeln!(out, "</node>")?;
+    }
+    for (i, link) in graph.links.iter().enumerate() {
+        writeln!(
+            out,
+            r#"    <edge id="e{i}" source="n{}" target="n{}"><data key="rel">{}</data></edge>"#,
+            link.source,
+            link.target,
+            xml(&link.rel)
+        )?;
+    }
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(out)
+}
+
+fn gexf(graph: &BrowseResponse) -> Result<String, fmt::Error> {
+    let columns = node_columns(graph);
+    let ids: BTreeMap<&str, String> = columns.keys().enumerate().map(|(i, name)| (name.as_str(), format!("p{i}"))).collect();
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">"#)?;
+    writeln!(out, r#"  <meta><creator>{}</creator></meta>"#, env!("CARGO_PKG_NAME"))?;
+    writeln!(out, r#"  <graph mode="static" defaultedgetype="directed">"#)?;
+    writeln!(out, r#"    <attributes class="node">"#)?;
+    writeln!(out, r#"      <attribute id="kind" title="kind" type="string"/>"#)?;
+    for (name, ty) in &columns {
+        writeln!(out, r#"      <attribute id="{}" title="{}" type="{}"/>"#, ids[name.as_str()], xml(name), ty.name())?;
+    }
+    writeln!(out, "    </attributes>")?;
+    writeln!(out, r#"    <attributes class="edge">"#)?;
+    writeln!(out, r#"      <attribute id="rel" title="rel" type="string"/>"#)?;
+    writeln!(out, "    </attributes>")?;
+
+    writeln!(out, "    <nodes>")?;
+    for (i, node) in graph.nodes.iter().enumerate() {
+        write!(out, r#"      <node id="n{i}" label="{}"><attvalues><attvalue for="kind" value="{}"/>"#, xml(&node.title), xml(&node.label))?;
+        for (key, value) in props(&node.props) {
+            write!(out, r#"<attvalue for="{}" value="{}"/>"#, ids[&*prop_name(key)], xml(&text(value)))?;
+        }
+        writeln!(out, "</attvalues></node>")?;
+    }
+    writeln!(out, "    </nodes>")?;
+
+    writeln!(out, "    <edges>")?;
+    for (i, link) in graph.links.iter().enumerate() {
+        let rel = xml(&link.rel);
+        writeln!(
+            out,
+            r#"      <edge id="e{i}" source="n{}" target="n{}" label="{rel}"><attvalues><attvalue for="rel" value="{rel}"/></attvalues></edge>"#,
+            link.source, link.target
+        )?;
+    }
+    writeln!(out, "    </edges>")?;
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</gexf>")?;
+    Ok(out)
+}
+
+/// JSON de elementos de Cytoscape.js (`cy.add(...)` / `cytoscape({ elements })`).
+fn cytoscape(graph: &BrowseResponse) -> Value {
+    let nodes: Vec<Value> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let mut data: Map<String, Value> =
+                props(&node.props).map(|(k, v)| (prop_name(k).into_owned(), v.clone())).collect();
+            data.insert("id".into(), format!("n{i}").into());
+            data.insert("label".into(), node.title.clone().into());
+            data.insert("kind".into(), node.label.clone().into());
+            json!({ "data": data })
+        })
+        .collect();
+    let edges: Vec<Value> = graph
+        .links
+        .iter()
+        .enumerate()
+        .map(|(i, link)| {
+            json!({ "data": {
+                "id": format!("e{i}"),
+                "source": format!("n{}", link.source),
+                "target": format!("n{}", link.target),
+                "rel": link.rel,
+            }})
+        })
+        .collect();
+    json!({ "elements": { "nodes": nodes, "edges": edges } })
+}
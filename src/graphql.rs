@@ -0,0 +1,428 @@
+// ============================
+// GraphQL (/graphql)
+// ============================
+//
+// Esquema de Movie, Person y las relaciones entre ambos (`Credit`: ACTED_IN, DIRECTED…)
+// con resolvers sobre `Service`. Cada nivel de la consulta se resuelve con un único
+// Cypher por tipo gracias a los `DataLoader` (`UNWIND $keys`), en vez de una consulta
+// por nodo (N+1). Las lecturas pasan por `guarded_read` con los bookmarks de la
+// petición, igual que la API REST; `vote` reutiliza `Service::vote`.
+//
+// Las consultas se limitan en profundidad y complejidad (`GRAPHQL_MAX_DEPTH`,
+// `GRAPHQL_MAX_COMPLEXITY`). `GET /graphql` sirve GraphQL Playground.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    http::{playground_source, GraphQLPlaygroundConfig},
+    ComplexObject, Context, EmptySubscription, ErrorExtensions as _, Object, Schema, SimpleObject,
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use color_eyre::eyre::{eyre, Result};
+use neo4rs::Row;
+use tracing::{error, Instrument as _};
+
+use crate::{
+    bookmarks::{BookmarkResponse, Bookmarks},
+    deadline::Cancellable,
+    tenant::Tenants,
+    AppError, Search, Service,
+};
+
+pub type MoviesSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+pub fn schema(max_depth: usize, max_complexity: usize) -> MoviesSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(max_depth)
+        .limit_complexity(max_complexity)
+        .finish()
+}
+
+// ============================
+// Tipos
+// ============================
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+struct Movie {
+    title: String,
+    released: Option<i64>,
+    tagline: Option<String>,
+    votes: Option<i64>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+struct Person {
+    name: String,
+    born: Option<i64>,
+}
+
+/// Relación de una persona con una película.
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+struct Credit {
+    /// Tipo de relación: ACTED_IN, DIRECTED, PRODUCED, WROTE, REVIEWED…
+    #[graphql(name = "type")]
+    rel_type: String,
+    /// Personajes (ACTED_IN)
+    roles: Vec<String>,
+    /// Reseña (REVIEWED)
+    summary: Option<String>,
+    rating: Option<i64>,
+    #[graphql(skip)]
+    person: String,
+    #[graphql(skip)]
+    movie: String,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+struct VotePayload {
+    votes: u64,
+    /// Bookmark de la escritura (también en la cabecera `x-neo4j-bookmark`)
+    bookmark: Option<String>,
+}
+
+/// Créditos filtrados por tipo de relación (sin filtro, todos).
+fn by_type(credits: Vec<Credit>, types: Option<Vec<String>>) -> Vec<Credit> {
+    let Some(types) = types else { return credits };
+    let types: Vec<String> = types.iter().map(|t| t.trim().to_ascii_uppercase()).collect();
+    credits.into_iter().filter(|c| types.contains(&c.rel_type)).collect()
+}
+
+#[ComplexObject]
+impl Movie {
+    /// Personas relacionadas con la película (reparto, dirección, guion…)
+    #[graphql(complexity = "5 * child_complexity")]
+    async fn credits(&self, ctx: &Context<'_>, types: Option<Vec<String>>) -> async_graphql::Result<Vec<Credit>> {
+        let credits = loaders(ctx).movie_credits.load_one(self.title.clone()).await?.unwrap_or_default();
+        Ok(by_type(credits, types))
+    }
+}
+
+#[ComplexObject]
+impl Person {
+    /// Películas en las que ha participado y con qué relación
+    #[graphql(complexity = "5 * child_complexity")]
+    async fn credits(&self, ctx: &Context<'_>, types: Option<Vec<String>>) -> async_graphql::Result<Vec<Credit>> {
+        let credits = loaders(ctx).person_credits.load_one(self.name.clone()).await?.unwrap_or_default();
+        Ok(by_type(credits, types))
+    }
+}
+
+#[ComplexObject]
+impl Credit {
+    async fn person(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Person>> {
+        loaders(ctx).people.load_one(self.person.clone()).await
+    }
+
+    async fn movie(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Movie>> {
+        loaders(ctx).movies.load_one(self.movie.clone()).await
+    }
+}
+
+// ============================
+// Raíces
+// ============================
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Película por título exacto
+    async fn movie(&self, ctx: &Context<'_>, title: String) -> async_graphql::Result<Option<Movie>> {
+        loaders(ctx).movies.load_one(title).await
+    }
+
+    /// Persona por nombre exacto
+    async fn person(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<Option<Person>> {
+        loaders(ctx).people.load_one(name).await
+    }
+
+    /// Películas cuyo título contiene `search` (como `GET /search`)
+    #[graphql(complexity = "limit.unwrap_or(25).clamp(1, 200) as usize * child_complexity")]
+    async fn movies(
+        &self,
+        ctx: &Context<'_>,
+        search: String,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<Movie>> {
+        let request = ctx.data_unchecked::<Arc<RequestContext>>();
+        let results = request
+            .service
+            .search(Search { q: search, offset, limit }, &request.bookmarks)
+            .await
+            .map_err(|e| gql_error(e.into()))?;
+        let movies: Vec<Movie> = results
+            .into_iter()
+            .filter_map(|r| {
+                Some(Movie {
+                    title: r.movie.title?,
+                    released: r.movie.released.map(i64::from),
+                    tagline: r.movie.tagline,
+                    votes: r.movie.votes.map(|v| v as i64),
+                })
+            })
+            .collect();
+        // Los hijos (`credits`…) ya no vuelven a pedir estas películas
+        loaders(ctx).movies.feed_many(movies.iter().map(|m| (m.title.clone(), m.clone()))).await;
+        Ok(movies)
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Suma un voto a la película (como `POST /movie/vote/:title`)
+    async fn vote(
+        &self,
+        ctx: &Context<'_>,
+        title: String,
+        idempotency_key: Option<String>,
+    ) -> async_graphql::Result<VotePayload> {
+        let request = ctx.data_unchecked::<Arc<RequestContext>>();
+        let title = crate::sanitize_title(title).map_err(gql_error)?;
+        let idempotency_key = idempotency_key.map(|k| k.trim().to_string());
+        if idempotency_key.as_ref().is_some_and(|k| k.is_empty() || k.len() > 200) {
+            return Err(gql_error(AppError::new(eyre!("invalid idempotency key"), StatusCode::BAD_REQUEST)));
+        }
+        let (result, bookmark) = request
+            .service
+            .vote(title, idempotency_key, &request.bookmarks)
+            .await
+            .map_err(|e| gql_error(e.into()))?;
+        *request.bookmark.lock().unwrap() = bookmark.clone();
+        Ok(VotePayload { votes: result.votes, bookmark })
+    }
+}
+
+/// Error GraphQL con el mismo contrato que `AppError`: sin detalles internos, con
+/// `status` y `error_id` (en las extensiones) para buscarlo en los logs.
+fn gql_error(err: AppError) -> async_graphql::Error {
+    let AppError { id, status, inner } = err;
+    error!(error_id=%id, status=%status, error=?inner, "graphql resolver failed");
+    async_graphql::Error::new("internal_error").extend_with(|_, e| {
+        e.set("status", status.as_u16());
+        e.set("error_id", id.to_string());
+    })
+}
+
+// ============================
+// DataLoaders
+// ============================
+
+/// Datos de una petición: el tenant resuelto, sus bookmarks y el de la escritura.
+struct RequestContext {
+    service: Service,
+    bookmarks: Bookmarks,
+    bookmark: Mutex<Option<String>>,
+}
+
+struct Loaders {
+    movies: DataLoader<MovieLoader>,
+    people: DataLoader<PersonLoader>,
+    movie_credits: DataLoader<MovieCreditsLoader>,
+    person_credits: DataLoader<PersonCreditsLoader>,
+}
+
+fn loaders<'a>(ctx: &Context<'a>) -> &'a Loaders {
+    ctx.data_unchecked::<Loaders>()
+}
+
+/// Fuente común de los loaders: una lectura con `$keys` y una conversión por fila.
+#[derive(Clone)]
+struct Batch {
+    service: Service,
+    bookmarks: Bookmarks,
+}
+
+impl Batch {
+    async fn rows<T>(
+        &self,
+        operation: &'static str,
+        cypher: &str,
+        keys: &[String],
+        convert: impl Fn(&Row) -> Result<T>,
+    ) -> async_graphql::Result<Vec<T>> {
+        let service = &self.service;
+        let cancel = Cancellable::new();
+        let q = cancel.query(cypher).param("keys", keys.to_vec());
+        service
+            .guarded_read(operation, service.timeouts.default, &cancel, || async {
+                let mut txn = service.read_txn(&self.bookmarks).await?;
+                let mut rows = service.execute(&mut txn, operation, q.clone()).await?;
+                let mut out = Vec::new();
+                while let Some(row) = rows.next(&mut txn).await? {
+                    out.push(convert(&row)?);
+                }
+                txn.commit().await?;
+                Ok(out)
+            })
+            .await
+            .map_err(|e| gql_error(e.into()))
+    }
+
+    async fn credits(&self, operation: &'static str, cypher: &str, keys: &[String]) -> async_graphql::Result<Vec<(String, Credit)>> {
+        self.rows(operation, cypher, keys, |row| {
+            let credit = Credit {
+                rel_type: row.get("type")?,
+                roles: row.get::<Option<Vec<String>>>("roles")?.unwrap_or_default(),
+                summary: row.get("summary")?,
+                rating: row.get("rating")?,
+                person: row.get("person")?,
+                movie: row.get("movie")?,
+            };
+            Ok((row.get("key")?, credit))
+        })
+        .await
+    }
+}
+
+fn group(rows: Vec<(String, Credit)>) -> HashMap<String, Vec<Credit>> {
+    let mut grouped: HashMap<String, Vec<Credit>> = HashMap::new();
+    for (key, credit) in rows {
+        grouped.entry(key).or_default().push(credit);
+    }
+    grouped
+}
+
+struct MovieLoader(Batch);
+
+impl Loader<String> for MovieLoader {
+    type Value = Movie;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Movie>, Self::Error> {
+        const MOVIES: &str = r#"
+            UNWIND $keys AS title
+            MATCH (m:Movie {title: title})
+            RETURN m.title AS title, m.released AS released, m.tagline AS tagline, m.votes AS votes
+        "#;
+        let movies = self
+            .0
+            .rows("graphql_movies", MOVIES, keys, |row| {
+                Ok(Movie {
+                    title: row.get("title")?,
+                    released: row.get("released")?,
+                    tagline: row.get("tagline")?,
+                    votes: row.get("votes")?,
+                })
+            })
+            .await?;
+        Ok(movies.into_iter().map(|m| (m.title.clone(), m)).collect())
+    }
+}
+
+struct PersonLoader(Batch);
+
+impl Loader<String> for PersonLoader {
+    type Value = Person;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Person>, Self::Error> {
+        const PEOPLE: &str = r#"
+            UNWIND $keys AS name
+            MATCH (p:Person {name: name})
+            RETURN p.name AS name, p.born AS born
+        "#;
+        let people = self
+            .0
+            .rows("graphql_people", PEOPLE, keys, |row| Ok(Person { name: row.get("name")?, born: row.get("born")? }))
+            .await?;
+        Ok(people.into_iter().map(|p| (p.name.clone(), p)).collect())
+    }
+}
+
+struct MovieCreditsLoader(Batch);
+
+impl Loader<String> for MovieCreditsLoader {
+    type Value = Vec<Credit>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<Credit>>, Self::Error> {
+        const MOVIE_CREDITS: &str = r#"
+            UNWIND $keys AS key
+            MATCH (m:Movie {title: key})<-[r]-(p:Person)
+            RETURN key, type(r) AS type, r.roles AS roles, r.summary AS summary, r.rating AS rating,
+                   p.name AS person, m.title AS movie
+        "#;
+        Ok(group(self.0.credits("graphql_movie_credits", MOVIE_CREDITS, keys).await?))
+    }
+}
+
+struct PersonCreditsLoader(Batch);
+
+impl Loader<String> for PersonCreditsLoader {
+    type Value = Vec<Credit>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<Credit>>, Self::Error> {
+        const PERSON_CREDITS: &str = r#"
+            UNWIND $keys AS key
+            MATCH (p:Person {name: key})-[r]->(m:Movie)
+            RETURN key, type(r) AS type, r.roles AS roles, r.summary AS summary, r.rating AS rating,
+                   p.name AS person, m.title AS movie
+        "#;
+        Ok(group(self.0.credits("graphql_person_credits", PERSON_CREDITS, keys).await?))
+    }
+}
+
+// ============================
+// Handlers
+// ============================
+
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body(content = Object, description = "Petición GraphQL: `{ \"query\", \"variables\", \"operationName\" }`"),
+    params(
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes)")
+    ),
+    responses(
+        (status = 200, description = "Respuesta GraphQL (`data` y/o `errors`; los errores llevan `status` y `error_id` en `extensions`)", body = Object)
+    )
+)]
+pub async fn graphql(
+    State(tenants): State<Tenants>,
+    service: Service,
+    bookmarks: Bookmarks,
+    Json(request): Json<async_graphql::Request>,
+) -> impl IntoResponse {
+    let batch = Batch { service: service.clone(), bookmarks: bookmarks.clone() };
+    let loaders = Loaders {
+        movies: DataLoader::new(MovieLoader(batch.clone()), spawn),
+        people: DataLoader::new(PersonLoader(batch.clone()), spawn),
+        movie_credits: DataLoader::new(MovieCreditsLoader(batch.clone()), spawn),
+        person_credits: DataLoader::new(PersonCreditsLoader(batch), spawn),
+    };
+    let context = RequestContext { service, bookmarks, bookmark: Mutex::default() };
+    let context = Arc::new(context);
+
+    let response = tenants.graphql.execute(request.data(loaders).data(context.clone())).await;
+    // El bookmark de `vote` sale también en cabecera/cookie, como en la API REST
+    let bookmark = context.bookmark.lock().unwrap().take();
+    (BookmarkResponse(bookmark), Json(response))
+}
+
+/// Los lotes de los DataLoader corren en tareas aparte: siguen en el span de la petición.
+fn spawn(batch: futures::future::BoxFuture<'static, ()>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(batch.in_current_span())
+}
+
+/// GraphQL Playground (los recursos se cargan del CDN, como el frontend).
+pub async fn playground() -> Html<String> {
+    Html(playground_source(GraphQLPlaygroundConfig::new("/graphql")))
+}
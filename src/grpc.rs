@@ -0,0 +1,239 @@
+// ============================
+// gRPC (MovieService, proto/movies.proto)
+// ============================
+//
+// Las mismas operaciones que la API HTTP sobre tonic, en el mismo puerto (HTTP/2 sin
+// TLS, h2c): las rutas `/movies.v1.MovieService/<Método>` se montan en el `Router`
+// de axum, así que comparten `Service`, la configuración, el request id, las métricas
+// de Prometheus y el resto de capas. El tenant y los bookmarks se leen de los
+// metadatos (`x-tenant`, `x-neo4j-bookmark`) con los mismos extractores que HTTP.
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use color_eyre::eyre::{eyre, Report};
+use futures::{channel::mpsc, SinkExt as _};
+use tonic::{metadata::MetadataValue, Code, Status};
+use tower::Layer as _;
+use tracing::error;
+
+use crate::{
+    bookmarks::{Bookmarks, BOOKMARK_HEADER},
+    formats::GraphRecord,
+    tenant::Tenants,
+    AppError, Browse, GraphSink, Search, Service,
+};
+
+pub mod pb {
+    tonic::include_proto!("movies.v1");
+}
+
+use pb::movie_service_server::{MovieService, MovieServiceServer};
+
+const METHODS: &[&str] = &["GetMovie", "Search", "Vote", "Browse"];
+/// Mensajes de `Browse` en vuelo (si el cliente lee despacio, se deja de pedir filas)
+const STREAM_CHANNEL_RECORDS: usize = 256;
+
+/// Una ruta por método (etiqueta `endpoint` propia en las métricas HTTP).
+pub fn router(tenants: Tenants) -> Router<Tenants> {
+    let service = middleware::from_fn_with_state(tenants, context).layer(MovieServiceServer::new(MovieGrpc));
+    METHODS.iter().fold(Router::new(), |router, method| {
+        router.route_service(&format!("/{}/{method}", pb::movie_service_server::SERVICE_NAME), service.clone())
+    })
+}
+
+/// Resuelve tenant y bookmarks como los handlers HTTP y los deja en las extensiones.
+async fn context(
+    service: Result<Service, AppError>,
+    bookmarks: Result<Bookmarks, AppError>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match service.and_then(|service| Ok((service, bookmarks?))) {
+        Ok((service, bookmarks)) => {
+            request.extensions_mut().insert(service);
+            request.extensions_mut().insert(bookmarks);
+            next.run(request).await
+        }
+        Err(err) => status(err).into_http().into_response(),
+    }
+}
+
+struct MovieGrpc;
+
+#[tonic::async_trait]
+impl MovieService for MovieGrpc {
+    async fn get_movie(&self, request: tonic::Request<pb::GetMovieRequest>) -> Result<tonic::Response<pb::Movie>, Status> {
+        let (service, bookmarks, request) = parts(request).ok_or_else(no_context)?;
+        let title = crate::sanitize_title(request.title).map_err(status)?;
+        match service.movie(title, &bookmarks).await {
+            Ok(Some(movie)) => Ok(tonic::Response::new(movie.into())),
+            Ok(None) => Err(status(AppError::new(eyre!("not found"), StatusCode::NOT_FOUND))),
+            Err(e) => Err(status(e.into())),
+        }
+    }
+
+    async fn search(&self, request: tonic::Request<pb::SearchRequest>) -> Result<tonic::Response<pb::SearchResponse>, Status> {
+        let (service, bookmarks, request) = parts(request).ok_or_else(no_context)?;
+        let search = Search { q: request.q, offset: request.offset, limit: request.limit };
+        let results = service.search(search, &bookmarks).await.map_err(|e| status(e.into()))?;
+        let movies = results.into_iter().map(|r| r.movie.into()).collect();
+        Ok(tonic::Response::new(pb::SearchResponse { movies }))
+    }
+
+    async fn vote(&self, request: tonic::Request<pb::VoteRequest>) -> Result<tonic::Response<pb::VoteResponse>, Status> {
+        let (service, bookmarks, request) = parts(request).ok_or_else(no_context)?;
+        let title = crate::sanitize_title(request.title).map_err(status)?;
+        let idempotency_key = request.idempotency_key.map(|k| k.trim().to_string());
+        if idempotency_key.as_ref().is_some_and(|k| k.is_empty() || k.len() > 200) {
+            return Err(status(AppError::new(eyre!("invalid idempotency key"), StatusCode::BAD_REQUEST)));
+        }
+        let (result, bookmark) = service.vote(title, idempotency_key, &bookmarks).await.map_err(|e| status(e.into()))?;
+
+        let mut response = tonic::Response::new(pb::VoteResponse { votes: result.votes, bookmark: bookmark.clone() });
+        if let Some(value) = bookmark.and_then(|b| MetadataValue::try_from(b).ok()) {
+            response.metadata_mut().insert(BOOKMARK_HEADER, value);
+        }
+        Ok(response)
+    }
+
+    type BrowseStream = mpsc::Receiver<Result<pb::GraphRecord, Status>>;
+
+    async fn browse(&self, request: tonic::Request<pb::BrowseRequest>) -> Result<tonic::Response<Self::BrowseStream>, Status> {
+        let (service, bookmarks, request) = parts(request).ok_or_else(no_context)?;
+        let (tx, rx) = mpsc::channel(STREAM_CHANNEL_RECORDS);
+        service
+            .spawn_graph_stream(request.into(), bookmarks, GrpcSink(tx))
+            .await
+            .map_err(|e| status(e.into()))?;
+        Ok(tonic::Response::new(rx))
+    }
+}
+
+/// Servicio y bookmarks que dejó `context`, y el mensaje.
+fn parts<T>(request: tonic::Request<T>) -> Option<(Service, Bookmarks, T)> {
+    let (_, extensions, message) = request.into_parts();
+    Some((extensions.get::<Service>()?.clone(), extensions.get::<Bookmarks>()?.clone(), message))
+}
+
+fn no_context() -> Status {
+    status(AppError::new(eyre!("grpc request without tenant context"), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Mismo contrato que `AppError` en HTTP: mensaje genérico y `x-error-id` en los
+/// metadatos para buscar el detalle en los logs.
+fn status(err: AppError) -> Status {
+    let AppError { id, status, inner } = err;
+    error!(error_id=%id, status=%status, error=?inner, "grpc call failed");
+    let code = match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::CONFLICT => Code::FailedPrecondition,
+        StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    };
+    let mut grpc = Status::new(code, "internal_error");
+    if let Ok(value) = MetadataValue::try_from(id.to_string()) {
+        grpc.metadata_mut().insert("x-error-id", value);
+    }
+    grpc
+}
+
+/// Registros de `Browse` como mensajes; un fallo a mitad termina el stream con su código.
+struct GrpcSink(mpsc::Sender<Result<pb::GraphRecord, Status>>);
+
+impl GraphSink for GrpcSink {
+    async fn record(&mut self, record: GraphRecord<'_>) -> bool {
+        self.0.send(Ok(record.into())).await.is_ok()
+    }
+
+    async fn flush(&mut self) {}
+
+    async fn abort(&mut self, err: Report) {
+        let _ = self.0.send(Err(status(err.into()))).await;
+    }
+}
+
+// ============================
+// Conversiones
+// ============================
+
+impl From<crate::Movie> for pb::Movie {
+    fn from(movie: crate::Movie) -> Self {
+        let cast = movie
+            .cast
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| pb::CastMember { name: p.name, job: p.job, roles: p.role.unwrap_or_default() })
+            .collect();
+        Self {
+            title: movie.title,
+            released: movie.released,
+            tagline: movie.tagline,
+            votes: movie.votes.map(|v| v as u64),
+            cast,
+        }
+    }
+}
+
+impl From<pb::BrowseRequest> for Browse {
+    fn from(request: pb::BrowseRequest) -> Self {
+        let csv = |values: Vec<String>| (!values.is_empty()).then(|| values.join(","));
+        Self {
+            limit: request.limit,
+            rel: csv(request.rel),
+            root: request.root,
+            depth: request.depth,
+            node_incl: csv(request.node_incl),
+            node_excl: csv(request.node_excl),
+            released_gte: request.released_gte,
+            released_lte: request.released_lte,
+        }
+    }
+}
+
+impl From<GraphRecord<'_>> for pb::GraphRecord {
+    fn from(record: GraphRecord<'_>) -> Self {
+        use pb::graph_record::Record;
+        let record = match record {
+            GraphRecord::Node { id, node } => Record::Node(pb::Node {
+                id: id as u64,
+                title: node.title.clone(),
+                label: node.label.clone(),
+                props: Some(to_struct(&node.props)),
+            }),
+            GraphRecord::Link(link) => Record::Link(pb::Link {
+                source: link.source as u64,
+                target: link.target as u64,
+                rel: link.rel.clone(),
+            }),
+            GraphRecord::End { nodes, links } => Record::End(pb::End { nodes: nodes as u64, links: links as u64 }),
+        };
+        Self { record: Some(record) }
+    }
+}
+
+/// Propiedades (mapa JSON) como `google.protobuf.Struct`.
+fn to_struct(props: &serde_json::Value) -> prost_types::Struct {
+    let fields = props.as_object().into_iter().flatten().map(|(k, v)| (k.clone(), to_value(v))).collect();
+    prost_types::Struct { fields }
+}
+
+fn to_value(value: &serde_json::Value) -> prost_types::Value {
+    use prost_types::value::Kind;
+    use serde_json::Value;
+    let kind = match value {
+        Value::Null => Kind::NullValue(0),
+        Value::Bool(b) => Kind::BoolValue(*b),
+        Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        Value::String(s) => Kind::StringValue(s.clone()),
+        Value::Array(items) => Kind::ListValue(prost_types::ListValue { values: items.iter().map(to_value).collect() }),
+        Value::Object(_) => Kind::StructValue(to_struct(value)),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
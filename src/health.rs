@@ -0,0 +1,213 @@
+// ============================
+// Liveness / Readiness
+// ============================
+//
+// - `/livez`  : sólo el proceso (no toca Neo4j). Si responde, el proceso está vivo.
+// - `/readyz` : Neo4j alcanzable (la base de datos de cada tenant), warmup
+//               completado y sin drenaje en curso. Devuelve el detalle por check
+//               (estado + latencia) en JSON.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::Result;
+use futures::future::{join_all, try_join_all};
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    breaker::BreakerSnapshot,
+    retry::{self, RetryPolicy},
+    migrations::{self, LockConfig},
+    schema::{self, SchemaMode},
+    tenant::Tenants,
+    Service,
+};
+
+/// Estado de preparación compartido entre el arranque, los handlers y el apagado.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    warmed_up: AtomicBool,
+    draining: AtomicBool,
+}
+
+impl Readiness {
+    pub fn mark_warmed_up(&self) {
+        self.warmed_up.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_warmed_up(&self) -> bool {
+        self.warmed_up.load(Ordering::SeqCst)
+    }
+
+    /// Marca el proceso como "drenando": `/readyz` pasa a 503 para que el
+    /// balanceador deje de enviar tráfico antes del apagado.
+    pub fn start_draining(&self) {
+        if !self.draining.swap(true, Ordering::SeqCst) {
+            warn!("draining: readiness will report not ready");
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CheckResult {
+    name: &'static str,
+    /// Tenant al que se refiere el check (sólo en los checks de Neo4j)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    status: CheckStatus,
+    /// Latencia del check en milisegundos (sólo para checks con E/S)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadyReport {
+    status: CheckStatus,
+    checks: Vec<CheckResult>,
+    /// Estado del circuit breaker de cada tenant (informativo: no afecta a `status`,
+    /// el ping de readiness no pasa por el breaker)
+    circuit_breakers: BTreeMap<String, BreakerSnapshot>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    responses(
+        (status = 200, description = "Process alive", body = String)
+    )
+)]
+pub async fn livez() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadyReport),
+        (status = 503, description = "Not ready (a tenant database is down, warmup pending or draining)", body = ReadyReport)
+    )
+)]
+pub async fn readyz(State(tenants): State<Tenants>) -> impl IntoResponse {
+    let report = check_readiness(&tenants).await;
+    let status = match report.status {
+        CheckStatus::Up => StatusCode::OK,
+        CheckStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
+
+async fn check_readiness(tenants: &Tenants) -> ReadyReport {
+    let timeout = tenants.readiness_timeout;
+    let mut checks = join_all(tenants.services().map(|service| async move {
+        let started = Instant::now();
+        let outcome = tokio::time::timeout(timeout, crate::warmup(&service.db)).await;
+        let latency_ms = Some(started.elapsed().as_millis() as u64);
+        let (status, error) = match outcome {
+            Ok(Ok(())) => (CheckStatus::Up, None),
+            Ok(Err(e)) => (CheckStatus::Down, Some(e.to_string())),
+            Err(_) => (CheckStatus::Down, Some(format!("timeout after {timeout:?}"))),
+        };
+        CheckResult { name: "neo4j", tenant: Some(service.tenant.to_string()), status, latency_ms, error }
+    }))
+    .await;
+
+    checks.push(CheckResult {
+        name: "warmup",
+        tenant: None,
+        status: if tenants.readiness.is_warmed_up() { CheckStatus::Up } else { CheckStatus::Down },
+        latency_ms: None,
+        error: None,
+    });
+
+    checks.push(CheckResult {
+        name: "not_draining",
+        tenant: None,
+        status: if tenants.readiness.is_draining() { CheckStatus::Down } else { CheckStatus::Up },
+        latency_ms: None,
+        error: None,
+    });
+
+    let status = if checks.iter().all(|c| c.status == CheckStatus::Up) {
+        CheckStatus::Up
+    } else {
+        CheckStatus::Down
+    };
+    let circuit_breakers = tenants.services().map(|s| (s.tenant.to_string(), s.breaker.snapshot())).collect();
+    ReadyReport { status, checks, circuit_breakers }
+}
+
+/// Pasos del warmup de cada tenant tras el primer ping correcto.
+#[derive(Debug, Clone, Copy)]
+pub struct StartupSteps {
+    pub schema: SchemaMode,
+    /// `Some` = aplicar migraciones pendientes (con cerrojo entre réplicas)
+    pub migrations: Option<LockConfig>,
+}
+
+/// Reintenta el warmup de cada tenant (backoff exponencial con jitter) hasta que
+/// todas sus bases de datos respondan y entonces marca el servicio como listo.
+/// Mientras tanto `/readyz` devuelve 503 y `/livez` sigue respondiendo. Falla si
+/// algún tenant agota los intentos de `policy` o falla uno de sus `steps`.
+pub async fn warmup_until_ready(tenants: Tenants, policy: RetryPolicy, steps: StartupSteps) -> Result<()> {
+    try_join_all(tenants.services().map(|service| warmup_tenant(service, policy, steps))).await?;
+    tenants.readiness.mark_warmed_up();
+    info!("warmup done, service ready");
+    Ok(())
+}
+
+async fn warmup_tenant(service: &Service, policy: RetryPolicy, steps: StartupSteps) -> Result<()> {
+    let tenant = &*service.tenant;
+    let mut attempt = 1;
+    loop {
+        match crate::warmup(&service.db).await {
+            Ok(()) => {
+                info!(tenant, attempt, "tenant warmup done");
+                // Con la BD ya accesible: restricciones e índices y migraciones (sin reintentos)
+                schema::sync(&service.db, tenant, steps.schema).await?;
+                if let Some(lock) = steps.migrations {
+                    migrations::up(&service.db, tenant, lock).await?;
+                }
+                return Ok(());
+            }
+            Err(e) if policy.exhausted(attempt) => {
+                return Err(e.wrap_err(format!("warmup of tenant `{tenant}` failed after {attempt} attempts")));
+            }
+            Err(e) => {
+                let delay = policy.delay(attempt);
+                let reason = retry::transient_reason(&e).unwrap_or("other");
+                metrics::counter!(
+                    "neo4j_retries_total",
+                    "tenant" => tenant.to_string(),
+                    "operation" => "startup",
+                    "reason" => reason
+                )
+                .increment(1);
+                warn!(tenant, error=?e, attempt, retry_in=?delay, "warmup query failed, not ready yet");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+// ============================
+// Importación de CSV (movies.csv, people.csv, roles.csv)
+// ============================
+//
+// Los ficheros se leen en streaming, registro a registro: la memoria queda acotada
+// al lote en curso (más las claves ya vistas, para detectar duplicados). Cada fila
+// se valida antes de escribirse; las válidas se insertan o actualizan con
+// `UNWIND … MERGE` en lotes de una transacción y las inválidas se informan con su
+// número de línea. Una columna opcional vacía no borra el valor existente.
+//
+//   movies.csv  title*, released, tagline
+//   people.csv  name*, born
+//   roles.csv   person*, movie*, type*, roles (ACTED_IN, separados por `;`),
+//               summary y rating (REVIEWED)
+//
+// Los roles sólo enlazan películas y personas que ya existen: van los últimos.
+
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+use color_eyre::eyre::{Report, Result, WrapErr as _};
+use csv_async::{AsyncReaderBuilder, StringRecord, Trim};
+use neo4rs::{BoltType, Graph};
+use serde::Serialize;
+use tokio::io::AsyncRead;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{dump::DumpReport, retry};
+
+/// Filas rechazadas que se detallan por fichero (el recuento sigue siendo exacto).
+pub const MAX_REJECTED_DETAILS: usize = 1000;
+
+/// Tipos de relación admitidos en `roles.csv`.
+const REL_TYPES: &[&str] = &["ACTED_IN", "DIRECTED", "PRODUCED", "WROTE", "REVIEWED"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvKind {
+    Movies,
+    People,
+    Roles,
+}
+
+impl CsvKind {
+    /// Por nombre de fichero o de campo multipart (`movies`, `movies.csv`…).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match name.strip_suffix(".csv").unwrap_or(&name) {
+            "movies" => Some(Self::Movies),
+            "people" => Some(Self::People),
+            "roles" => Some(Self::Roles),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Movies => "movies",
+            Self::People => "people",
+            Self::Roles => "roles",
+        }
+    }
+
+    fn required_columns(self) -> &'static [&'static str] {
+        match self {
+            Self::Movies => &["title"],
+            Self::People => &["name"],
+            Self::Roles => &["person", "movie", "type"],
+        }
+    }
+}
+
+/// Resultado de importar un fichero.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FileReport {
+    pub file: CsvKind,
+    pub inserted: u64,
+    pub updated: u64,
+    /// Total de filas rechazadas (el detalle se corta en 1000)
+    pub rejected_count: u64,
+    pub rejected: Vec<RejectedRow>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RejectedRow {
+    /// Línea del fichero (la cabecera es la 1)
+    pub line: u64,
+    pub reason: String,
+}
+
+/// Respuesta de `/admin/import`: CSV (`files`) o volcado JSONL (`dump`).
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ImportReport {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dump: Option<DumpReport>,
+}
+
+impl FileReport {
+    fn new(file: CsvKind) -> Self {
+        Self { file, inserted: 0, updated: 0, rejected_count: 0, rejected: Vec::new() }
+    }
+
+    fn reject(&mut self, line: u64, reason: impl Into<String>) {
+        self.rejected_count += 1;
+        if self.rejected.len() < MAX_REJECTED_DETAILS {
+            self.rejected.push(RejectedRow { line, reason: reason.into() });
+        }
+    }
+
+    /// Un lote que Neo4j rechaza entero invalida todas sus filas. Si la base de
+    /// datos no está disponible no tiene sentido seguir: se aborta la importación.
+    fn reject_batch(&mut self, lines: impl Iterator<Item = u64>, err: Report) -> Result<()> {
+        if retry::transient_reason(&err).is_some() {
+            return Err(err.wrap_err("import aborted"));
+        }
+        warn!(file = self.file.as_str(), error=?err, "import batch rejected");
+        for line in lines {
+            self.reject(line, format!("batch failed: {err}"));
+        }
+        Ok(())
+    }
+}
+
+/// Fila validada, lista para el `UNWIND`.
+struct ValidRow {
+    line: u64,
+    /// Clave de deduplicación dentro del fichero
+    key: String,
+    /// Tipo de relación (sólo roles)
+    rel: Option<&'static str>,
+    fields: HashMap<&'static str, BoltType>,
+}
+
+/// Importa un CSV en la base de datos en lotes de `batch_size` filas.
+pub async fn import_csv<R>(db: &Graph, tenant: &str, kind: CsvKind, input: R, batch_size: usize) -> Result<FileReport>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let mut reader = AsyncReaderBuilder::new().trim(Trim::All).create_reader(input);
+    let mut report = FileReport::new(kind);
+
+    let headers = reader.headers().await.wrap_err("cannot read CSV header")?;
+    let columns = match Columns::new(kind, headers) {
+        Ok(columns) => columns,
+        Err(reason) => {
+            report.reject(1, reason);
+            return Ok(report);
+        }
+    };
+
+    let batch_size = batch_size.max(1);
+    let mut batch: Vec<ValidRow> = Vec::with_capacity(batch_size);
+    // Primera línea de cada clave: un duplicado en el mismo fichero se rechaza
+    let mut seen: HashMap<String, u64> = HashMap::new();
+    let mut record = StringRecord::new();
+
+    loop {
+        match reader.read_record(&mut record).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) if e.is_io_error() => return Err(e).wrap_err("cannot read CSV"),
+            // Registro mal formado (columnas de más/menos, UTF-8 inválido): se salta
+            Err(e) => {
+                report.reject(e.position().map_or(0, |p| p.line()), e.to_string());
+                continue;
+            }
+        }
+        let line = record.position().map_or(0, |p| p.line());
+
+        let row = validate(kind, &columns, &record, line).and_then(|row| match seen.entry(row.key.clone()) {
+            Entry::Occupied(first) => Err(format!("duplicate of line {}", first.get())),
+            Entry::Vacant(slot) => {
+                slot.insert(line);
+                Ok(row)
+            }
+        });
+        match row {
+            Ok(row) => batch.push(row),
+            Err(reason) => report.reject(line, reason),
+        }
+
+        if batch.len() >= batch_size {
+            flush(db, kind, &mut batch, &mut report).await?;
+        }
+    }
+    flush(db, kind, &mut batch, &mut report).await?;
+
+    for (outcome, count) in
+        [("inserted", report.inserted), ("updated", report.updated), ("rejected", report.rejected_count)]
+    {
+        metrics::counter!(
+            "import_rows_total",
+            "tenant" => tenant.to_string(),
+            "file" => kind.as_str(),
+            "outcome" => outcome
+        )
+        .increment(count);
+    }
+    info!(
+        tenant,
+        file = kind.as_str(),
+        inserted = report.inserted,
+        updated = report.updated,
+        rejected = report.rejected_count,
+        "csv imported"
+    );
+    Ok(report)
+}
+
+// ============================
+// Validación
+// ============================
+
+/// Posición de cada columna conocida en la cabecera.
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    fn new(kind: CsvKind, headers: &StringRecord) -> Result<Self, String> {
+        let columns: HashMap<String, usize> =
+            headers.iter().enumerate().map(|(i, h)| (h.trim().to_ascii_lowercase(), i)).collect();
+        let missing: Vec<&str> = kind.required_columns().iter().copied().filter(|c| !columns.contains_key(*c)).collect();
+        if !missing.is_empty() {
+            return Err(format!("missing column(s) {}", missing.join(", ")));
+        }
+        Ok(Self(columns))
+    }
+
+    /// Valor no vacío de la columna `name`.
+    fn get<'r>(&self, record: &'r StringRecord, name: &str) -> Option<&'r str> {
+        self.0.get(name).and_then(|i| record.get(*i)).filter(|v| !v.is_empty())
+    }
+
+    fn required<'r>(&self, record: &'r StringRecord, name: &str) -> Result<&'r str, String> {
+        self.get(record, name).ok_or_else(|| format!("`{name}` is required"))
+    }
+}
+
+fn validate(kind: CsvKind, columns: &Columns, record: &StringRecord, line: u64) -> Result<ValidRow, String> {
+    let mut fields: HashMap<&'static str, BoltType> = HashMap::from([("line", (line as i64).into())]);
+
+    match kind {
+        CsvKind::Movies => {
+            let title = text(columns.required(record, "title")?, "title", 200)?;
+            let released = integer(columns.get(record, "released"), "released", 1870..=2100)?;
+            let tagline = columns.get(record, "tagline").map(|t| text(t, "tagline", 1000)).transpose()?;
+            fields.extend([("title", title.into()), ("released", released.into()), ("tagline", tagline.into())]);
+            Ok(ValidRow { line, key: title.to_string(), rel: None, fields })
+        }
+        CsvKind::People => {
+            let name = text(columns.required(record, "name")?, "name", 200)?;
+            let born = integer(columns.get(record, "born"), "born", 1800..=2100)?;
+            fields.extend([("name", name.into()), ("born", born.into())]);
+            Ok(ValidRow { line, key: name.to_string(), rel: None, fields })
+        }
+        CsvKind::Roles => {
+            let person = text(columns.required(record, "person")?, "person", 200)?;
+            let movie = text(columns.required(record, "movie")?, "movie", 200)?;
+            let rel_type = columns.required(record, "type")?.to_ascii_uppercase();
+            let rel = REL_TYPES
+                .iter()
+                .copied()
+                .find(|t| *t == rel_type)
+                .ok_or_else(|| format!("invalid type `{rel_type}` (expected one of {})", REL_TYPES.join(", ")))?;
+
+            let roles: Option<Vec<String>> = columns.get(record, "roles").map(|r| {
+                r.split(';').map(str::trim).filter(|r| !r.is_empty()).map(str::to_string).collect()
+            });
+            let summary = columns.get(record, "summary").map(|s| text(s, "summary", 1000)).transpose()?;
+            let rating = integer(columns.get(record, "rating"), "rating", 0..=100)?;
+            if roles.is_some() && rel != "ACTED_IN" {
+                return Err("`roles` only applies to ACTED_IN".into());
+            }
+            if (summary.is_some() || rating.is_some()) && rel != "REVIEWED" {
+                return Err("`summary`/`rating` only apply to REVIEWED".into());
+            }
+
+            fields.extend([
+                ("person", person.into()),
+                ("movie", movie.into()),
+                ("roles", roles.into()),
+                ("summary", summary.into()),
+                ("rating", rating.into()),
+            ]);
+            Ok(ValidRow { line, key: format!("{rel}\u{0}{person}\u{0}{movie}"), rel: Some(rel), fields })
+        }
+    }
+}
+
+fn text<'a>(value: &'a str, column: &str, max_len: usize) -> Result<&'a str, String> {
+    if value.chars().count() > max_len {
+        return Err(format!("`{column}` longer than {max_len} characters"));
+    }
+    Ok(value)
+}
+
+fn integer(value: Option<&str>, column: &str, range: std::ops::RangeInclusive<i64>) -> Result<Option<i64>, String> {
+    let Some(value) = value else { return Ok(None) };
+    match value.parse::<i64>() {
+        Ok(n) if range.contains(&n) => Ok(Some(n)),
+        Ok(_) => Err(format!("`{column}` out of range {}..={}", range.start(), range.end())),
+        Err(_) => Err(format!("`{column}` is not an integer: `{value}`")),
+    }
+}
+
+// ============================
+// Escritura
+// ============================
+
+// `existing` se mira antes del MERGE para distinguir inserción de actualización
+// (las claves del lote son únicas: los duplicados ya se rechazaron)
+const MOVIES: &str = r#"
+    UNWIND $rows AS row
+    OPTIONAL MATCH (existing:Movie {title: row.title})
+    WITH row, existing IS NULL AS created
+    MERGE (m:Movie {title: row.title})
+    ON CREATE SET m.votes = 0
+    SET m.released = coalesce(row.released, m.released),
+        m.tagline = coalesce(row.tagline, m.tagline)
+    RETURN count(CASE WHEN created THEN 1 END) AS inserted
+"#;
+const PEOPLE: &str = r#"
+    UNWIND $rows AS row
+    OPTIONAL MATCH (existing:Person {name: row.name})
+    WITH row, existing IS NULL AS created
+    MERGE (p:Person {name: row.name})
+    SET p.born = coalesce(row.born, p.born)
+    RETURN count(CASE WHEN created THEN 1 END) AS inserted
+"#;
+// `{type}` sale de `REL_TYPES`, nunca del fichero tal cual
+const ROLES: &str = r#"
+    UNWIND $rows AS row
+    OPTIONAL MATCH (p:Person {name: row.person})
+    OPTIONAL MATCH (m:Movie {title: row.movie})
+    WITH row, p, m,
+         CASE WHEN p IS NULL OR m IS NULL THEN false ELSE exists { (p)-[:{type}]->(m) } END AS existed
+    FOREACH (_ IN CASE WHEN p IS NOT NULL AND m IS NOT NULL THEN [1] ELSE [] END |
+        MERGE (p)-[r:{type}]->(m)
+        SET r.roles = coalesce(row.roles, r.roles),
+            r.summary = coalesce(row.summary, r.summary),
+            r.rating = coalesce(row.rating, r.rating))
+    RETURN row.line AS line, row.person AS person, row.movie AS movie,
+           p IS NOT NULL AS has_person, m IS NOT NULL AS has_movie, existed
+"#;
+
+async fn flush(db: &Graph, kind: CsvKind, batch: &mut Vec<ValidRow>, report: &mut FileReport) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let rows = std::mem::take(batch);
+
+    match kind {
+        CsvKind::Movies | CsvKind::People => {
+            let query = if kind == CsvKind::Movies { MOVIES } else { PEOPLE };
+            let lines: Vec<u64> = rows.iter().map(|r| r.line).collect();
+            match upsert_nodes(db, query, rows).await {
+                Ok(inserted) => {
+                    report.inserted += inserted;
+                    report.updated += lines.len() as u64 - inserted;
+                }
+                Err(e) => report.reject_batch(lines.into_iter(), e)?,
+            }
+        }
+        CsvKind::Roles => {
+            // Una consulta por tipo de relación (el tipo no puede ser un parámetro)
+            let mut by_type: BTreeMap<&'static str, Vec<ValidRow>> = BTreeMap::new();
+            for row in rows {
+                by_type.entry(row.rel.unwrap_or_default()).or_default().push(row);
+            }
+            for (rel, rows) in by_type {
+                let lines: Vec<u64> = rows.iter().map(|r| r.line).collect();
+                if let Err(e) = upsert_roles(db, rel, rows, report).await {
+                    report.reject_batch(lines.into_iter(), e)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn param(rows: Vec<ValidRow>) -> Vec<BoltType> {
+    rows.into_iter().map(|r| r.fields.into()).collect()
+}
+
+/// Devuelve cuántas filas del lote eran nodos nuevos.
+async fn upsert_nodes(db: &Graph, query: &str, rows: Vec<ValidRow>) -> Result<u64> {
+    let mut result = db.execute(neo4rs::query(query).param("rows", param(rows))).await?;
+    let inserted: i64 = result.single().await?.get("inserted")?;
+    Ok(inserted as u64)
+}
+
+async fn upsert_roles(db: &Graph, rel: &str, rows: Vec<ValidRow>, report: &mut FileReport) -> Result<()> {
+    let query = ROLES.replace("{type}", rel);
+    let mut result = db.execute(neo4rs::query(&query).param("rows", param(rows))).await?;
+
+    // Se acumula aparte: si el lote falla a mitad, `reject_batch` lo cuenta entero
+    let mut outcome = FileReport::new(CsvKind::Roles);
+    while let Some(row) = result.next().await? {
+        let line = row.get::<i64>("line")? as u64;
+        if !row.get::<bool>("has_person")? {
+            outcome.reject(line, format!("unknown person `{}`", row.get::<String>("person")?));
+        } else if !row.get::<bool>("has_movie")? {
+            outcome.reject(line, format!("unknown movie `{}`", row.get::<String>("movie")?));
+        } else if row.get::<bool>("existed")? {
+            outcome.updated += 1;
+        } else {
+            outcome.inserted += 1;
+        }
+    }
+
+    report.inserted += outcome.inserted;
+    report.updated += outcome.updated;
+    // El total cuenta todas las rechazadas; el detalle sigue acotado a `MAX_REJECTED_DETAILS`
+    report.rejected_count += outcome.rejected_count;
+    let room = MAX_REJECTED_DETAILS.saturating_sub(report.rejected.len());
+    report.rejected.extend(outcome.rejected.into_iter().take(room));
+    Ok(())
+}
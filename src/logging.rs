@@ -0,0 +1,214 @@
+// ============================
+// Logs: formato (texto/JSON) y filtro ajustable en caliente
+// ============================
+//
+// `LOG_FORMAT=json` escribe un objeto JSON por línea (campos del evento en la raíz
+// y los spans activos en `span`/`spans`). El span `request` de cada petición lleva
+// `request_id` y `route` (la plantilla de la ruta, p. ej. `/movie/:title`), y el evento
+// "finished processing request" añade `status`, `latency_ms` y, si hubo error, el
+// `error_id` que también va en el cuerpo de la respuesta.
+//
+// El `EnvFilter` (de `RUST_LOG` al arrancar) va detrás de un `reload::Layer`:
+// `PUT /admin/log-level` lo sustituye sin reiniciar.
+
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, Span};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    admin::Admin,
+    codec::{Accepts, Decoded, Encoded},
+    tenant::Tenants,
+    AppError,
+};
+
+/// Formato de los logs (`LOG_FORMAT`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// Un objeto JSON por línea
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" | "" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(eyre!("invalid log format `{other}` (text|json)")),
+        }
+    }
+}
+
+/// Filtro de logs activo; se cambia con `/admin/log-level`.
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directivas del arranque (`DELETE /admin/log-level` vuelve a ellas)
+    initial: Arc<str>,
+}
+
+impl LogFilter {
+    /// Capa de filtro recargable para `tracing_subscriber::registry()`.
+    pub fn new(directives: &str) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(directives));
+        (layer, Self { handle, initial: directives.into() })
+    }
+
+    fn current(&self) -> Result<String> {
+        Ok(self.handle.with_current(|filter| filter.to_string())?)
+    }
+
+    fn set(&self, directives: &str) -> Result<(), AppError> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| AppError::new(eyre!("invalid log filter: {e}"), StatusCode::BAD_REQUEST))?;
+        self.handle.reload(filter).map_err(|e| AppError::from(eyre!(e)))
+    }
+}
+
+impl fmt::Debug for LogFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogFilter").field("initial", &self.initial).finish_non_exhaustive()
+    }
+}
+
+// ============================
+// Campos del span `request`
+// ============================
+
+/// `error_id` de una respuesta de error (lo pone `AppError`; lo lee `on_response`).
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorId(pub Uuid);
+
+/// `route_layer` del Router: sólo aquí se conoce la ruta que casó.
+pub async fn record_route(route: MatchedPath, req: Request, next: Next) -> Response {
+    Span::current().record("route", route.as_str());
+    next.run(req).await
+}
+
+/// Sustituye a `DefaultOnResponse`: mismo mensaje, con `latency_ms` numérico y `error_id`.
+pub fn on_response<B>(response: &axum::http::Response<B>, latency: Duration, _span: &Span) {
+    let error_id = response.extensions().get::<ErrorId>().map(|id| id.0.to_string());
+    info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        error_id,
+        "finished processing request"
+    );
+}
+
+// ============================
+// Handlers
+// ============================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogLevelInput {
+    /// Directivas de `EnvFilter` (sintaxis de `RUST_LOG`)
+    #[schema(example = "info,movies_rust_bolt[graph]=debug")]
+    pub filter: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogLevel {
+    /// Filtro activo
+    pub filter: String,
+    /// Filtro del arranque (`RUST_LOG`)
+    pub initial: String,
+}
+
+impl LogLevel {
+    fn of(log_filter: &LogFilter) -> Result<Self, AppError> {
+        Ok(Self { filter: log_filter.current()?, initial: log_filter.initial.to_string() })
+    }
+}
+
+fn log_filter(tenants: &Tenants) -> Result<&LogFilter, AppError> {
+    tenants
+        .admin
+        .log_filter
+        .as_ref()
+        .ok_or_else(|| AppError::from(eyre!("log filter is not reloadable in this process")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Filtro de logs activo y el del arranque", body = LogLevel),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn get_level(
+    _admin: Admin,
+    Accepts(codec): Accepts,
+    State(tenants): State<Tenants>,
+) -> Result<Encoded<LogLevel>, AppError> {
+    Ok(Encoded(codec, LogLevel::of(log_filter(&tenants)?)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    tag = "admin",
+    request_body(content = LogLevelInput, description = "Nuevo filtro (p. ej. `info,movies_rust_bolt[graph]=debug` para el span de `Service::graph`)"),
+    responses(
+        (status = 200, description = "Filtro aplicado (afecta a todo el proceso, no sólo al tenant)", body = LogLevel),
+        (status = 400, description = "Directivas inválidas"),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn set_level(
+    _admin: Admin,
+    Accepts(codec): Accepts,
+    State(tenants): State<Tenants>,
+    Decoded(input): Decoded<LogLevelInput>,
+) -> Result<Encoded<LogLevel>, AppError> {
+    let log_filter = log_filter(&tenants)?;
+    let previous = log_filter.current()?;
+    log_filter.set(&input.filter)?;
+    let level = LogLevel::of(log_filter)?;
+    info!(%previous, filter = %level.filter, "log filter changed");
+    Ok(Encoded(codec, level))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Vuelve al filtro del arranque", body = LogLevel),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn reset_level(
+    _admin: Admin,
+    Accepts(codec): Accepts,
+    State(tenants): State<Tenants>,
+) -> Result<Encoded<LogLevel>, AppError> {
+    let log_filter = log_filter(&tenants)?;
+    log_filter.set(&log_filter.initial)?;
+    let level = LogLevel::of(log_filter)?;
+    info!(filter = %level.filter, "log filter reset");
+    Ok(Encoded(codec, level))
+}
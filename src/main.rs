@@ -1,20 +1,33 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::Infallible,
+    future::{Future, IntoFuture as _},
+    hash::{BuildHasher as _, RandomState},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
-    extract::{Path, Query, State},
-    http::{HeaderName, HeaderValue, StatusCode},
+    body::{Body, Bytes},
+    extract::{Path, Query},
+    http::{
+        header::{CONTENT_TYPE, VARY},
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
     response::{IntoResponse, Redirect, Response},
     routing::{get, post},
-    serve, Json, Router,
+    serve, Router, ServiceExt,
 };
 use axum::http::Method;
 use axum_prometheus::PrometheusMetricLayer;
+use clap::Parser as _;
 use color_eyre::eyre::{eyre, Report, Result};
-use futures::TryStreamExt as _;
-use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
-use neo4rs::{query, ConfigBuilder, Graph, Node as NeoNode};
+use futures::{channel::mpsc, SinkExt as _, StreamExt as _};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use neo4rs::{ConfigBuilder, Graph, Node as NeoNode, Operation, Txn};
 use serde::{Deserialize, Serialize};
-use tower::limit::ConcurrencyLimitLayer;
+use tower::{limit::ConcurrencyLimitLayer, Layer as _};
 use tower_http::{
     cors::{Any, CorsLayer},
     compression::CompressionLayer,
@@ -23,18 +36,63 @@ use tower_http::{
     services::ServeDir,
     set_header::SetResponseHeaderLayer,
     timeout::TimeoutLayer,
-    trace::{DefaultMakeSpan, DefaultOnFailure, DefaultOnRequest, DefaultOnResponse, TraceLayer},
+    trace::{DefaultOnFailure, DefaultOnRequest, TraceLayer},
     sensitive_headers::SetSensitiveHeadersLayer,
 };
-use tracing::{debug, error, info, instrument, Level};
+use tracing::{debug, error, info, instrument, warn, Instrument as _, Level};
 use tracing_error::ErrorLayer;
-use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
 use uuid::Uuid;
 
 // --- OpenAPI / Swagger ---
 use utoipa::{OpenApi, ToSchema, IntoParams};
 use utoipa_swagger_ui::SwaggerUi;
 
+mod admin;
+mod bookmarks;
+mod breaker;
+mod cli;
+mod codec;
+mod console;
+mod cypher;
+mod deadline;
+mod dump;
+mod formats;
+mod graphql;
+mod grpc;
+mod health;
+mod import;
+mod logging;
+mod migrations;
+mod plan;
+mod pool;
+mod querylog;
+mod retry;
+mod schema;
+mod seed;
+mod table;
+mod telemetry;
+mod tenant;
+mod views;
+
+use admin::{AdminConfig, SecurityAddon};
+use bookmarks::{BookmarkResponse, Bookmarks};
+use codec::{Accepts, Codec, CodecAddon, Decoded, Encoded};
+use breaker::{BreakerConfig, CircuitBreaker, CircuitOpen};
+use deadline::{Cancellable, QueryTimeout, QueryTimeouts};
+use formats::{FormatQuery, GraphFormat, GraphRecord, STREAM_MAX_LIMIT};
+use health::Readiness;
+use logging::{ErrorId, LogFilter, LogFormat};
+use plan::PlanRequest;
+use pool::{PoolGate, PoolTimeout};
+use querylog::{LoggedRows, QueryLog};
+use retry::RetryPolicy;
+use schema::SchemaMode;
+use table::TableQuery;
+use telemetry::{OtlpConfig, Telemetry};
+use tenant::{TenantConfig, Tenants};
+use tokio::sync::oneshot;
+
 // ============================
 // Config
 // ============================
@@ -46,10 +104,75 @@ struct AppConfig {
     neo4j_uri: String,
     neo4j_user: String,
     neo4j_password: String,
+    /// Base de datos del tenant implícito cuando no se define `TENANTS`
     neo4j_database: String,
+    /// Tenants `nombre=base_de_datos,...` (cada uno con su conexión; ver `tenant.rs`)
+    tenants: Option<String>,
+    /// Tenant de las peticiones que no indican ninguno (por defecto, el primero)
+    default_tenant: Option<String>,
+    /// Tamaño máximo del pool de conexiones Bolt
+    neo4j_max_connections: usize,
+    /// Filas pedidas por cada PULL de Bolt
+    neo4j_fetch_size: usize,
+    /// Espera máxima por una conexión libre del pool
+    neo4j_acquire_timeout_ms: u64,
     request_timeout_secs: u64,
+    /// Timeouts de consulta por operación (búsqueda, grafo, caminos con root/depth, resto)
+    query_timeout_search_ms: u64,
+    query_timeout_graph_ms: u64,
+    query_timeout_path_ms: u64,
+    query_timeout_default_ms: u64,
+    /// Duración máxima de `/graph` en streaming (NDJSON)
+    query_timeout_stream_ms: u64,
+    /// Timeout de la consola Cypher (`POST /query`)
+    query_timeout_console_ms: u64,
     max_concurrency: usize,
     max_body_bytes: usize,
+    /// Timeout del ping a Neo4j en `/readyz`
+    readiness_timeout_ms: u64,
+    /// Backoff inicial / máximo de los reintentos contra Neo4j (arranque y lecturas)
+    db_retry_initial_ms: u64,
+    db_retry_max_ms: u64,
+    /// Intentos máximos de una lectura idempotente (incluido el primero)
+    db_read_attempts: u32,
+    /// Intentos máximos del warmup de arranque (0 = sin límite)
+    startup_attempts: u32,
+    /// Circuit breaker: fallos seguidos que lo abren, umbral de llamada lenta y tiempo abierto
+    breaker_failure_threshold: u32,
+    breaker_slow_call_ms: u64,
+    breaker_open_secs: u64,
+    /// Tiempo que `/readyz` responde 503 antes de cerrar el servidor tras SIGTERM
+    shutdown_drain_secs: u64,
+    /// Restricciones e índices al arrancar: off | check (falla si faltan) | apply
+    schema_on_startup: SchemaMode,
+    /// Aplica las migraciones pendientes en el warmup
+    migrate_on_startup: bool,
+    /// Caducidad del cerrojo de migraciones y espera máxima por él
+    migration_lock_ttl_secs: u64,
+    migration_lock_wait_secs: u64,
+    /// Token de portador de `/admin/*` (sin él, la API de administración responde 403)
+    admin_token: Option<String>,
+    /// Límites de las rutas `/admin/*` (cuerpo y duración), mayores que los de la API
+    admin_max_body_bytes: usize,
+    admin_request_timeout_secs: u64,
+    /// Filas por transacción de `/admin/import`
+    import_batch_size: usize,
+    /// Máximo de filas de la consola Cypher (`POST /query`)
+    console_max_rows: usize,
+    /// Límites de `/graphql`: profundidad de anidamiento y complejidad (campos ponderados)
+    graphql_max_depth: usize,
+    graphql_max_complexity: usize,
+    /// Consultas más lentas que esto se registran con `warn` ("slow query")
+    slow_query_ms: u64,
+    /// Vida de las claves de idempotencia del voto (después se borran)
+    idempotency_key_ttl_secs: u64,
+    /// Colector OTLP/gRPC de las trazas (sin él no se exportan; ver `telemetry.rs`)
+    otel_endpoint: Option<String>,
+    otel_service_name: String,
+    /// Fracción de trazas muestreadas (las que llegan con `traceparent` siguen al llamante)
+    otel_sampling_ratio: f64,
+    /// Formato de los logs: text | json
+    log_format: LogFormat,
 }
 
 impl Default for AppConfig {
@@ -61,10 +184,111 @@ impl Default for AppConfig {
             neo4j_user: std::env::var("NEO4J_USER").unwrap_or_else(|_| "movies".to_string()),
             neo4j_password: std::env::var("NEO4J_PASSWORD").unwrap_or_else(|_| "movies".to_string()),
             neo4j_database: std::env::var("NEO4J_DATABASE").unwrap_or_else(|_| "movies".to_string()),
+            tenants: std::env::var("TENANTS").ok().filter(|s| !s.trim().is_empty()),
+            default_tenant: std::env::var("DEFAULT_TENANT").ok().filter(|s| !s.trim().is_empty()),
+            neo4j_max_connections: std::env::var("NEO4J_MAX_CONNECTIONS").ok().and_then(|s| s.parse().ok()).unwrap_or(16),
+            neo4j_fetch_size: std::env::var("NEO4J_FETCH_SIZE").ok().and_then(|s| s.parse().ok()).unwrap_or(200),
+            neo4j_acquire_timeout_ms: std::env::var("NEO4J_ACQUIRE_TIMEOUT_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(5_000),
             port: std::env::var("PORT").ok().and_then(|s| s.parse().ok()).unwrap_or(8080),
             request_timeout_secs: std::env::var("REQUEST_TIMEOUT_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(20),
+            query_timeout_search_ms: std::env::var("QUERY_TIMEOUT_SEARCH_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(5_000),
+            query_timeout_graph_ms: std::env::var("QUERY_TIMEOUT_GRAPH_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(10_000),
+            query_timeout_path_ms: std::env::var("QUERY_TIMEOUT_PATH_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(15_000),
+            query_timeout_default_ms: std::env::var("QUERY_TIMEOUT_DEFAULT_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(5_000),
+            query_timeout_stream_ms: std::env::var("QUERY_TIMEOUT_STREAM_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(300_000),
+            query_timeout_console_ms: std::env::var("QUERY_TIMEOUT_CONSOLE_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(30_000),
             max_concurrency: std::env::var("MAX_CONCURRENCY").ok().and_then(|s| s.parse().ok()).unwrap_or(512),
             max_body_bytes: std::env::var("MAX_BODY_BYTES").ok().and_then(|s| s.parse().ok()).unwrap_or(1_048_576),
+            readiness_timeout_ms: std::env::var("READINESS_TIMEOUT_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(2000),
+            db_retry_initial_ms: std::env::var("DB_RETRY_INITIAL_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(100),
+            db_retry_max_ms: std::env::var("DB_RETRY_MAX_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(5_000),
+            db_read_attempts: std::env::var("DB_READ_ATTEMPTS").ok().and_then(|s| s.parse().ok()).unwrap_or(3),
+            startup_attempts: std::env::var("STARTUP_ATTEMPTS").ok().and_then(|s| s.parse().ok()).unwrap_or(0),
+            breaker_failure_threshold: std::env::var("BREAKER_FAILURE_THRESHOLD").ok().and_then(|s| s.parse().ok()).unwrap_or(5),
+            breaker_slow_call_ms: std::env::var("BREAKER_SLOW_CALL_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(5_000),
+            breaker_open_secs: std::env::var("BREAKER_OPEN_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(10),
+            shutdown_drain_secs: std::env::var("SHUTDOWN_DRAIN_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(5),
+            schema_on_startup: std::env::var("SCHEMA_ON_STARTUP").ok().and_then(|s| s.parse().ok()).unwrap_or_default(),
+            migrate_on_startup: std::env::var("MIGRATE_ON_STARTUP").ok().and_then(|s| s.parse().ok()).unwrap_or(false),
+            migration_lock_ttl_secs: std::env::var("MIGRATION_LOCK_TTL_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(900),
+            migration_lock_wait_secs: std::env::var("MIGRATION_LOCK_WAIT_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(300),
+            admin_token: std::env::var("ADMIN_TOKEN").ok().filter(|s| !s.trim().is_empty()),
+            admin_max_body_bytes: std::env::var("ADMIN_MAX_BODY_BYTES").ok().and_then(|s| s.parse().ok()).unwrap_or(268_435_456),
+            admin_request_timeout_secs: std::env::var("ADMIN_REQUEST_TIMEOUT_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(900),
+            import_batch_size: std::env::var("IMPORT_BATCH_SIZE").ok().and_then(|s| s.parse().ok()).unwrap_or(1000),
+            console_max_rows: std::env::var("CONSOLE_MAX_ROWS").ok().and_then(|s| s.parse().ok()).unwrap_or(1000),
+            graphql_max_depth: std::env::var("GRAPHQL_MAX_DEPTH").ok().and_then(|s| s.parse().ok()).unwrap_or(8),
+            graphql_max_complexity: std::env::var("GRAPHQL_MAX_COMPLEXITY").ok().and_then(|s| s.parse().ok()).unwrap_or(1000),
+            slow_query_ms: std::env::var("SLOW_QUERY_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(500),
+            idempotency_key_ttl_secs: std::env::var("IDEMPOTENCY_KEY_TTL_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(86_400),
+            otel_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|s| !s.trim().is_empty()),
+            otel_service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string()),
+            otel_sampling_ratio: std::env::var("OTEL_TRACES_SAMPLER_ARG").ok().and_then(|s| s.parse().ok()).unwrap_or(1.0),
+            log_format: std::env::var("LOG_FORMAT").ok().and_then(|s| s.parse().ok()).unwrap_or_default(),
+        }
+    }
+}
+
+impl AppConfig {
+    fn read_retry(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.db_read_attempts.max(1),
+            initial_backoff: Duration::from_millis(self.db_retry_initial_ms),
+            max_backoff: Duration::from_millis(self.db_retry_max_ms),
+        }
+    }
+
+    fn startup_retry(&self) -> RetryPolicy {
+        RetryPolicy { max_attempts: self.startup_attempts, ..self.read_retry() }
+    }
+
+    fn query_timeouts(&self) -> QueryTimeouts {
+        QueryTimeouts {
+            search: Duration::from_millis(self.query_timeout_search_ms),
+            graph: Duration::from_millis(self.query_timeout_graph_ms),
+            path: Duration::from_millis(self.query_timeout_path_ms),
+            default: Duration::from_millis(self.query_timeout_default_ms),
+            stream: Duration::from_millis(self.query_timeout_stream_ms),
+            console: Duration::from_millis(self.query_timeout_console_ms),
+        }
+    }
+
+    fn migration_lock(&self) -> migrations::LockConfig {
+        migrations::LockConfig {
+            ttl: Duration::from_secs(self.migration_lock_ttl_secs),
+            wait: Duration::from_secs(self.migration_lock_wait_secs),
+        }
+    }
+
+    fn startup_steps(&self) -> health::StartupSteps {
+        health::StartupSteps {
+            schema: self.schema_on_startup,
+            migrations: self.migrate_on_startup.then(|| self.migration_lock()),
+        }
+    }
+
+    fn admin(&self) -> AdminConfig {
+        AdminConfig {
+            token: self.admin_token.as_deref().map(Into::into),
+            import_batch_size: self.import_batch_size.max(1),
+            console_max_rows: self.console_max_rows.max(1),
+            log_filter: None,
+        }
+    }
+
+    fn otlp(&self) -> Option<OtlpConfig> {
+        self.otel_endpoint.as_ref().map(|endpoint| OtlpConfig {
+            endpoint: endpoint.clone(),
+            service_name: self.otel_service_name.clone(),
+            sampling_ratio: self.otel_sampling_ratio,
+        })
+    }
+
+    fn breaker(&self) -> BreakerConfig {
+        BreakerConfig {
+            failure_threshold: self.breaker_failure_threshold.max(1),
+            slow_call: Duration::from_millis(self.breaker_slow_call_ms),
+            open_for: Duration::from_secs(self.breaker_open_secs),
         }
     }
 }
@@ -75,6 +299,7 @@ impl Default for AppConfig {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = cli::Cli::parse();
     color_eyre::install()?;
 
     // Provider de crypto (ring) para Rustls 0.23
@@ -82,64 +307,133 @@ async fn main() -> Result<()> {
         .install_default()
         .expect("failed to install rustls ring provider");
 
+    let cfg = AppConfig::default();
+
+    // Trazas OTLP (opcional); se vacían al soltar `telemetry`, al salir de main
+    let telemetry = cfg.otlp().map(Telemetry::install).transpose()?;
+    // Filtro recargable desde /admin/log-level
+    let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|s| EnvFilter::try_new(s).is_ok())
+        .unwrap_or_else(|| "info,tower_http=info,axum::rejection=trace".to_string());
+    let (filter, log_filter) = LogFilter::new(&directives);
+    let json = cfg.log_format == LogFormat::Json;
+    // Los subcomandos escriben datos en stdout (p. ej. `export` sin `-o`): los logs van a stderr
+    let to_stderr = cli.command.is_some();
+    let writer = move || -> BoxMakeWriter {
+        if to_stderr { BoxMakeWriter::new(std::io::stderr) } else { BoxMakeWriter::new(std::io::stdout) }
+    };
     tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info,tower_http=info,axum::rejection=trace".into()))
-        .with(tracing_subscriber::fmt::layer())
+        .with(filter)
+        .with((!json).then(|| tracing_subscriber::fmt::layer().with_writer(writer())))
+        .with(json.then(|| tracing_subscriber::fmt::layer().json().flatten_event(true).with_writer(writer())))
         .with(ErrorLayer::default())
+        .with(telemetry.as_ref().map(|t| tracing_opentelemetry::layer().with_tracer(t.tracer())))
         .init();
 
-    let cfg = AppConfig::default();
+    // Subcomandos de operación (schema, …): se ejecutan y terminan
+    if let Some(command) = cli.command {
+        return cli::run(command, &cfg).await;
+    }
 
     // Prometheus (exponemos /metrics)
     let prom_handle: PrometheusHandle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(querylog::DURATION_METRIC.to_string()), querylog::DURATION_BUCKETS)
+        .expect("invalid histogram buckets")
         .install_recorder()
         .expect("failed to install prometheus recorder");
     let prom_layer = PrometheusMetricLayer::new();
 
-    let db = db(&cfg)?;
-    if let Err(e) = warmup(&db).await {
-        error!(error=?e, "warmup query failed");
+    // Un Service (conexión, pool, breaker) por tenant
+    let tenant_configs = tenant::configs(&cfg)?;
+    let mut services = Vec::with_capacity(tenant_configs.len());
+    for t in &tenant_configs {
+        services.push(Service {
+            tenant: t.name.as_str().into(),
+            db: db(t)?,
+            retry: cfg.read_retry(),
+            breaker: Arc::new(CircuitBreaker::new(&t.name, cfg.breaker())),
+            pool: Arc::new(PoolGate::new(
+                &t.name,
+                t.max_connections.max(1),
+                Duration::from_millis(cfg.neo4j_acquire_timeout_ms),
+            )),
+            timeouts: cfg.query_timeouts(),
+            slow_query: Duration::from_millis(cfg.slow_query_ms),
+        });
+        info!(tenant = %t.name, database = %t.database, uri = %t.uri, "tenant configured");
     }
-
-    let service = Service { db };
+    let default_tenant = cfg.default_tenant.as_deref().unwrap_or(&tenant_configs[0].name);
+    let tenants = Tenants::new(
+        services,
+        default_tenant,
+        Arc::new(Readiness::default()),
+        Duration::from_millis(cfg.readiness_timeout_ms),
+        AdminConfig { log_filter: Some(log_filter), ..cfg.admin() },
+        graphql::schema(cfg.graphql_max_depth.max(1), cfg.graphql_max_complexity.max(1)),
+    )?;
+
+    // El tráfico queda condicionado a /readyz: hasta que el warmup tenga éxito
+    // el servicio se anuncia como no listo (y /livez sigue respondiendo).
+    // Si se agotan STARTUP_ATTEMPTS (o falla el esquema / una migración) el proceso termina con error.
+    let warmup_task = tokio::spawn(health::warmup_until_ready(tenants.clone(), cfg.startup_retry(), cfg.startup_steps()));
+    tokio::spawn(expire_idempotency_keys(tenants.clone(), Duration::from_secs(cfg.idempotency_key_ttl_secs.max(1))));
+    let readiness = tenants.readiness.clone();
 
     let assets_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
 
     // CORS
     let cors = CorsLayer::new()
         .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST])
-        .allow_headers(Any);
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static(bookmarks::BOOKMARK_HEADER)]);
 
     use axum::http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
 
+    // Rutas de administración: autenticadas y con límites propios (importaciones largas)
+    let admin = Router::new()
+        .route("/admin/import", post(admin::import))
+        .route("/admin/export", get(admin::export))
+        .route("/query", post(console::query))
+        .route("/admin/log-level", get(logging::get_level).put(logging::set_level).delete(logging::reset_level))
+        .layer(RequestBodyLimitLayer::new(cfg.admin_max_body_bytes))
+        .layer(TimeoutLayer::new(Duration::from_secs(cfg.admin_request_timeout_secs)));
+
     // --- Router + Swagger UI ---
     let app = Router::new()
         .route("/", get(|| async { Redirect::temporary("/index.html") }))
-        .route("/health", get(health))
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
         .route("/metrics", get({
             let h = prom_handle.clone();
             move || async move { h.render() }
         }))
         .route("/movie/:title", get(movie))
         .route("/movie/vote/:title", post(vote))
-        .route("/search", get(search))
+        .route("/search", get(search).post(search_body))
         .route("/graph", get(graph))
+        .route("/export/table", get(table::export))
+        .route("/views", get(views::list).post(views::create))
+        .route("/views/:slug", get(views::load).put(views::update).delete(views::delete))
+        .route("/graphql", get(graphql::playground).post(graphql::graphql))
+        // gRPC (h2c en el mismo puerto)
+        .merge(grpc::router(tenants.clone()))
         // Swagger UI en /docs y JSON en /api-docs/openapi.json
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .fallback_service(ServeDir::new(assets_dir))
-        .with_state(service)
+        .layer(RequestBodyLimitLayer::new(cfg.max_body_bytes))
+        .layer(TimeoutLayer::new(Duration::from_secs(cfg.request_timeout_secs)))
+        .merge(admin)
+        .route_layer(axum::middleware::from_fn(logging::record_route))
+        .with_state(tenants)
         // middlewares
         .layer(prom_layer)
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(
-                    DefaultMakeSpan::new()
-                        .level(Level::INFO)
-                        .include_headers(false),
-                )
+                .make_span_with(telemetry::make_span::<axum::body::Body>)
                 .on_request(DefaultOnRequest::new().level(Level::INFO))
-                .on_response(DefaultOnResponse::new().level(Level::INFO))
+                .on_response(logging::on_response)
                 .on_failure(DefaultOnFailure::new().level(Level::ERROR)),
         )
         .layer(SetSensitiveHeadersLayer::new([AUTHORIZATION, COOKIE, SET_COOKIE]))
@@ -162,17 +456,26 @@ async fn main() -> Result<()> {
         ))
         .layer(cors)
         .layer(CompressionLayer::new())
-        .layer(ConcurrencyLimitLayer::new(cfg.max_concurrency))
-        .layer(RequestBodyLimitLayer::new(cfg.max_body_bytes))
-        .layer(TimeoutLayer::new(Duration::from_secs(cfg.request_timeout_secs)));
+        .layer(ConcurrencyLimitLayer::new(cfg.max_concurrency));
 
     let addr = SocketAddr::from((cfg.bind_host, cfg.port));
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("listening on {}", listener.local_addr().unwrap());
 
-    serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // El prefijo `/t/<tenant>` se quita antes del enrutado, envolviendo el Router
+    let app = axum::middleware::map_request(tenant::strip_path_prefix).layer(app);
+    let server = serve(listener, ServiceExt::<axum::extract::Request>::into_make_service(app))
+        .with_graceful_shutdown(shutdown_signal(readiness, Duration::from_secs(cfg.shutdown_drain_secs)));
+    let mut server = std::pin::pin!(server.into_future());
+
+    tokio::select! {
+        res = &mut server => res?,
+        res = warmup_task => {
+            // Warmup agotado => abortamos; si terminó bien seguimos sirviendo
+            res??;
+            server.await?;
+        }
+    }
 
     Ok(())
 }
@@ -189,19 +492,47 @@ async fn main() -> Result<()> {
         description = "Demo Axum + Neo4j con grafo y métricas"
     ),
     paths(
-        health,
+        health::livez,
+        health::readyz,
         movie,
         vote,
         search,
-        graph
+        search_body,
+        graph,
+        table::export,
+        views::list,
+        views::load,
+        views::create,
+        views::update,
+        views::delete,
+        graphql::graphql,
+        admin::import,
+        admin::export,
+        console::query,
+        logging::get_level,
+        logging::set_level,
+        logging::reset_level
     ),
     components(
         schemas(
-            Movie, MovieResult, Person, VoteResult, BrowseResponse, Node, Link, Search, Browse
+            Movie, MovieResult, Person, VoteResult, BrowseResponse, Node, Link, Search, Browse, table::Table,
+            health::ReadyReport, health::CheckResult, health::CheckStatus,
+            breaker::BreakerSnapshot, breaker::BreakerState,
+            admin::ImportUpload, import::ImportReport, import::FileReport, import::RejectedRow, import::CsvKind,
+            dump::DumpReport,
+            console::ConsoleQuery, console::ConsoleResult,
+            logging::LogLevelInput, logging::LogLevel,
+            plan::PlanReport, plan::PlanNode, plan::PlanMode, plan::MissingIndex, plan::PlanNotification,
+            views::ViewInput, views::SavedView, views::SavedQuery
         )
     ),
+    modifiers(&SecurityAddon, &CodecAddon),
     tags(
-        (name = "movies", description = "Operaciones sobre películas")
+        (name = "movies", description = "Operaciones sobre películas"),
+        (name = "views", description = "Vistas guardadas (filtros de `/graph` o consultas de la consola)"),
+        (name = "graphql", description = "API GraphQL (Movie, Person y sus relaciones)"),
+        (name = "health", description = "Probes de liveness/readiness"),
+        (name = "admin", description = "Administración (token de portador `ADMIN_TOKEN`)")
     )
 )]
 struct ApiDoc;
@@ -210,7 +541,7 @@ struct ApiDoc;
 // Infra
 // ============================
 
-async fn shutdown_signal() {
+async fn shutdown_signal(readiness: Arc<Readiness>, drain: Duration) {
     #[cfg(unix)]
     {
         use tokio::signal::unix::{signal, SignalKind};
@@ -226,72 +557,100 @@ async fn shutdown_signal() {
     {
         let _ = tokio::signal::ctrl_c().await;
     }
-    info!("shutdown signal received, stopping server...");
+    info!("shutdown signal received, draining for {:?}...", drain);
+    readiness.start_draining();
+    tokio::time::sleep(drain).await;
+    info!("stopping server...");
 }
 
-fn db(cfg: &AppConfig) -> Result<Graph> {
+fn db(tenant: &TenantConfig) -> Result<Graph> {
+    // El `user_agent` del HELLO de Bolt no es configurable: neo4rs 0.9 envía siempre "neo4rs"
     let config = ConfigBuilder::new()
-        .uri(&cfg.neo4j_uri)
-        .user(&cfg.neo4j_user)
-        .password(&cfg.neo4j_password)
-        .db(cfg.neo4j_database.as_str())
+        .uri(&tenant.uri)
+        .user(&tenant.user)
+        .password(&tenant.password)
+        .db(tenant.database.as_str())
+        .max_connections(tenant.max_connections.max(1))
+        .fetch_size(tenant.fetch_size.max(1))
         .build()?;
 
     Ok(Graph::connect(config)?)
 }
 
+/// Un solo intento: `execute_read` reintenta por su cuenta (hasta ~60 s) por debajo del
+/// backoff de arranque y de `/readyz`, y esos intentos no cuentan en `neo4j_retries_total`.
 async fn warmup(db: &Graph) -> Result<()> {
     const PING: &str = "RETURN 1 AS ok";
-    let mut rows = db.execute(neo4rs::query(PING)).await?;
-    let _ok: i64 = rows.single().await?.get("ok")?;
+    let mut txn = db.start_txn_as(Operation::Read, None).await?;
+    let mut rows = txn.execute(neo4rs::query(PING)).await?;
+    let _ok: i64 = rows.single(txn.handle()).await?.get("ok")?;
+    txn.commit().await?;
     Ok(())
 }
 
+/// Borra periódicamente las `:IdempotencyKey` más antiguas que `ttl`: pasado ese plazo
+/// un reintento con la misma clave vuelve a votar.
+async fn expire_idempotency_keys(tenants: Tenants, ttl: Duration) {
+    const EXPIRE: &str = r#"
+        MATCH (k:IdempotencyKey) WHERE k.created_at < timestamp() - $ttl_ms
+        CALL { WITH k DELETE k } IN TRANSACTIONS OF 1000 ROWS
+    "#;
+    let period = (ttl / 4).clamp(Duration::from_secs(60), Duration::from_secs(3600));
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        for service in tenants.services() {
+            let q = neo4rs::query(EXPIRE).param("ttl_ms", ttl.as_millis() as i64);
+            match service.db.run(q).await {
+                Ok(summary) => {
+                    let deleted = summary.stats().nodes_deleted;
+                    if deleted > 0 {
+                        info!(tenant = %service.tenant, deleted, "expired idempotency keys removed");
+                    }
+                }
+                Err(e) => warn!(tenant = %service.tenant, error=?e, "failed to remove expired idempotency keys"),
+            }
+        }
+    }
+}
+
 // ============================
 // Handlers
 // ============================
 
-#[utoipa::path(
-    get,
-    path = "/health",
-    tag = "movies",
-    responses(
-        (status = 200, description = "Service healthy", body = String)
-    )
-)]
-async fn health(State(service): State<Service>) -> Result<impl IntoResponse, AppError> {
-    const PING: &str = "RETURN 1 AS ok";
-    let mut rows = service.db.execute(neo4rs::query(PING)).await?;
-    let ok: i64 = rows.single().await?.get("ok")?;
-    if ok == 1 {
-        Ok((StatusCode::OK, "ok"))
-    } else {
-        Err(AppError::new(eyre!("healthcheck failed"), StatusCode::SERVICE_UNAVAILABLE))
-    }
-}
-
 #[utoipa::path(
     get,
     path = "/movie/{title}",
     tag = "movies",
     params(
-        ("title" = String, Path, description = "Movie title (exact match)")
+        ("title" = String, Path, description = "Movie title (exact match)"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes); también vale la cookie `neo4j_bookmark`"),
+        plan::PlanQuery
     ),
     responses(
-        (status = 200, description = "Movie detail", body = Movie),
-        (status = 404, description = "Movie not found")
+        (status = 200, description = "Movie detail (con `explain`/`profile`: `{result, plan}`)", body = Movie),
+        (status = 401, description = "`explain`/`profile` without a valid admin token"),
+        (status = 404, description = "Movie or tenant not found")
     )
 )]
 async fn movie(
     Path(title): Path<String>,
-    State(service): State<Service>,
-) -> Result<Json<Movie>, AppError> {
+    PlanRequest(plan): PlanRequest,
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<Response, AppError> {
     let title = sanitize_title(title)?;
-    match service.movie(title).await {
-        Ok(Some(movie)) => Ok(Json(movie)),
-        Ok(None) => Err(AppError::new(eyre!("not found"), StatusCode::NOT_FOUND)),
-        Err(e) => Err(AppError::from(e)),
-    }
+    let movie = match service.movie(title.clone(), &bookmarks).await {
+        Ok(Some(movie)) => movie,
+        Ok(None) => return Err(AppError::new(eyre!("not found"), StatusCode::NOT_FOUND)),
+        Err(e) => return Err(AppError::from(e)),
+    };
+    let plan = PlanRequest(plan)
+        .run(&service, "movie_plan", service.timeouts.default, || movie_query(&Cancellable::new(), title), &bookmarks)
+        .await?;
+    Ok(plan::respond(codec, movie, plan))
 }
 
 #[utoipa::path(
@@ -299,88 +658,250 @@ async fn movie(
     path = "/movie/vote/{title}",
     tag = "movies",
     params(
-        ("title" = String, Path, description = "Movie title (exact match)")
+        ("title" = String, Path, description = "Movie title (exact match)"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Clave de idempotencia: permite reintentar el voto sin duplicarlo")
     ),
     responses(
-        (status = 200, description = "Vote counter increased", body = VoteResult),
-        (status = 404, description = "Movie not found")
+        (status = 200, description = "Vote counter increased", body = VoteResult,
+            headers(("x-neo4j-bookmark" = String, description = "Bookmark de la escritura (también en la cookie `neo4j_bookmark`)"))),
+        (status = 404, description = "Movie or tenant not found"),
+        (status = 409, description = "Idempotency-Key already used to vote for another movie")
     )
 )]
 async fn vote(
     Path(title): Path<String>,
-    State(service): State<Service>,
-) -> Result<Json<VoteResult>, AppError> {
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+    headers: HeaderMap,
+) -> Result<(BookmarkResponse, Encoded<VoteResult>), AppError> {
     let title = sanitize_title(title)?;
-    Ok(Json(service.vote(title).await?))
+    let idempotency_key = idempotency_key(&headers)?;
+    let (votes, bookmark) = service.vote(title, idempotency_key, &bookmarks).await?;
+    Ok((BookmarkResponse(bookmark), Encoded(codec, votes)))
 }
 
 #[utoipa::path(
     get,
     path = "/search",
     tag = "movies",
-    params(Search),
+    params(
+        Search,
+        plan::PlanQuery,
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes)")
+    ),
     responses(
-        (status = 200, description = "Search results", body = [MovieResult])
+        (status = 200, description = "Search results (con `explain`/`profile`: `{result, plan}`)", body = [MovieResult]),
+        (status = 401, description = "`explain`/`profile` without a valid admin token")
     )
 )]
 async fn search(
     Query(search): Query<Search>,
-    State(service): State<Service>,
-) -> Result<Json<Vec<MovieResult>>, AppError> {
-    Ok(Json(service.search(search).await?))
+    plan: PlanRequest,
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<Response, AppError> {
+    search_with_plan(search, plan, codec, service, bookmarks).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/search",
+    tag = "movies",
+    request_body(content = Search, description = "Los mismos parámetros que `GET /search`, en JSON, MessagePack o CBOR según `Content-Type`"),
+    params(
+        plan::PlanQuery,
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes)")
+    ),
+    responses(
+        (status = 200, description = "Search results (con `explain`/`profile`: `{result, plan}`)", body = [MovieResult]),
+        (status = 400, description = "Invalid body"),
+        (status = 401, description = "`explain`/`profile` without a valid admin token"),
+        (status = 415, description = "Unsupported Content-Type")
+    )
+)]
+async fn search_body(
+    plan: PlanRequest,
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+    Decoded(search): Decoded<Search>,
+) -> Result<Response, AppError> {
+    search_with_plan(search, plan, codec, service, bookmarks).await
+}
+
+async fn search_with_plan(
+    search: Search,
+    plan: PlanRequest,
+    codec: Codec,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<Response, AppError> {
+    let movies = service.search(search.clone(), &bookmarks).await?;
+    let plan = plan
+        .run(&service, "search_plan", service.timeouts.search, || search_query(&Cancellable::new(), search), &bookmarks)
+        .await?;
+    Ok(plan::respond(codec, movies, plan))
 }
 
 #[utoipa::path(
     get,
     path = "/graph",
     tag = "movies",
-    params(Browse),
+    params(
+        Browse,
+        views::ViewQuery,
+        plan::PlanQuery,
+        FormatQuery,
+        TableQuery,
+        ("Accept" = Option<String>, Header, description = "application/json, application/msgpack, application/cbor, application/graphml+xml, application/gexf+xml, application/vnd.cytoscape.js+json, application/vnd.apache.arrow.stream o application/x-ndjson (streaming, `limit` hasta 100000)"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`"),
+        ("x-neo4j-bookmark" = Option<String>, Header, description = "Bookmark de una escritura previa (read-your-writes)")
+    ),
     responses(
-        (status = 200, description = "Graph sub-sample", body = BrowseResponse)
+        (status = 200, description = "Graph sub-sample", content(
+            ("application/json" = BrowseResponse),
+            ("application/graphml+xml" = String),
+            ("application/gexf+xml" = String),
+            ("application/vnd.cytoscape.js+json" = Object),
+            ("application/x-ndjson" = String),
+            ("application/vnd.apache.arrow.stream" = String)
+        )),
+        (status = 400, description = "Unknown format, `view` is a console query, or `explain`/`profile` with a non-JSON/MessagePack/CBOR format"),
+        (status = 401, description = "`explain`/`profile` without a valid admin token"),
+        (status = 404, description = "Unknown `view`"),
+        (status = 406, description = "No acceptable format in Accept")
     )
 )]
 async fn graph(
     Query(browse): Query<Browse>,
-    State(service): State<Service>,
-) -> Result<Json<BrowseResponse>, AppError> {
-    Ok(Json(service.graph(browse).await?))
+    Query(view): Query<views::ViewQuery>,
+    Query(table): Query<TableQuery>,
+    format: GraphFormat,
+    plan: PlanRequest,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<Response, AppError> {
+    let browse = match view.view.as_deref() {
+        Some(slug) => views::apply(&service, slug, browse, &bookmarks).await?,
+        None => browse,
+    };
+    if plan.0.is_some() {
+        // El plan va junto al `BrowseResponse`: sólo en las codificaciones genéricas
+        let Some(codec) = format.codec() else {
+            return Err(AppError::new(
+                eyre!("explain/profile need format json, msgpack or cbor"),
+                StatusCode::BAD_REQUEST,
+            ));
+        };
+        let graph = service.graph(browse.clone(), &bookmarks).await?;
+        let GraphQuery { query, is_path, .. } = graph_query(browse, 1000);
+        let timeout = if is_path { service.timeouts.path } else { service.timeouts.graph };
+        let plan = plan.run(&service, "graph_plan", timeout, || query, &bookmarks).await?;
+        return Ok(plan::respond(codec, graph, plan));
+    }
+    if format == GraphFormat::Ndjson {
+        return Ok(service.graph_stream(browse, bookmarks).await?);
+    }
+    Ok(format.render(&service.graph(browse, &bookmarks).await?, table.table))
 }
 
 // ============================
 // Service & dominio
 // ============================
 
+/// Acceso a la base de datos de un tenant.
 #[derive(Clone)]
 struct Service {
+    /// Nombre del tenant (etiqueta `tenant` de las métricas)
+    tenant: Arc<str>,
     db: Graph,
+    /// Política de reintentos para lecturas idempotentes
+    retry: RetryPolicy,
+    /// Falla rápido (503) cuando Neo4j está caído o saturado
+    breaker: Arc<CircuitBreaker>,
+    /// Huecos del pool de conexiones (timeout de adquisición + métricas)
+    pool: Arc<PoolGate>,
+    timeouts: QueryTimeouts,
+    /// Umbral del slow query log (ver `querylog.rs`)
+    slow_query: Duration,
 }
 
 impl Service {
+    /// Lectura protegida: breaker → reintentos → hueco del pool → timeout/cancelación.
+    async fn guarded_read<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        timeout: Duration,
+        cancel: &Cancellable,
+        mut attempt: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let op = retry::read(&self.retry, &self.tenant, operation, || {
+            self.pool.run(cancel.run(&self.db, &self.tenant, operation, Operation::Read, timeout, attempt()))
+        });
+        self.breaker.call(operation, op).await
+    }
+
+    /// Transacción READ (enrutada a followers en un clúster) que respeta los bookmarks del cliente.
+    async fn read_txn(&self, bookmarks: &Bookmarks) -> Result<Txn> {
+        // En el heap, como `execute`: los futuros de neo4rs son grandes y en debug se
+        // acumulan en la pila de cada capa (breaker, reintentos, pool, timeout)
+        Ok(Box::pin(self.db.start_txn_as(Operation::Read, bookmarks.to_option())).await?)
+    }
+
+    /// Medición de la consulta `name` (ver `querylog.rs`); empieza al crearse.
+    fn query_log(&self, name: &'static str, q: &neo4rs::Query) -> QueryLog {
+        QueryLog::start(name, self.tenant.clone(), self.slow_query, q)
+    }
+
+    /// `txn.execute` con registro: duración, filas y contadores por consulta `name`.
+    async fn execute(&self, txn: &mut Txn, name: &'static str, q: neo4rs::Query) -> Result<LoggedRows> {
+        let log = self.query_log(name, &q);
+        let rows = Box::pin(txn.execute(q)).await?;
+        Ok(LoggedRows::new(rows, log))
+    }
+
+    /// Igual que `guarded_read`, pero sólo reintenta si hay clave de idempotencia.
+    async fn guarded_write<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        idempotency_key: Option<&str>,
+        cancel: &Cancellable,
+        mut attempt: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let timeout = self.timeouts.default;
+        let op = retry::write(&self.retry, &self.tenant, operation, idempotency_key, || {
+            self.pool.run(cancel.run(&self.db, &self.tenant, operation, Operation::Write, timeout, attempt()))
+        });
+        self.breaker.call(operation, op).await
+    }
+
     /// Devuelve Some(Movie) si existe, None si no.
-    #[instrument(skip(self))]
-    async fn movie(&self, title: String) -> Result<Option<Movie>> {
-        const FIND_MOVIE: &str = r#"
-            MATCH (movie:Movie {title:$title})
-            OPTIONAL MATCH (movie)<-[r]-(person:Person)
-            WITH movie.title AS title,
-                 movie.tagline AS tagline,
-                 movie.released AS released,
-                 movie.votes AS votes,
-                 collect({
-                    name: person.name,
-                    job: head(split(toLower(type(r)),'_')),
-                    role: r.roles
-                 }) AS cast
-            RETURN title, tagline, released, votes, cast
-            LIMIT 1
-        "#;
+    #[instrument(skip(self, bookmarks), fields(tenant = %self.tenant))]
+    async fn movie(&self, title: String, bookmarks: &Bookmarks) -> Result<Option<Movie>> {
+        let cancel = Cancellable::new();
+        let q = movie_query(&cancel, title);
+        self.guarded_read("movie", self.timeouts.default, &cancel, || self.fetch_movie(q.clone(), bookmarks))
+            .await
+    }
 
-        let mut rows = self
-            .db
-            .execute(neo4rs::query(FIND_MOVIE).param("title", title))
-            .await?;
+    async fn fetch_movie(&self, q: neo4rs::Query, bookmarks: &Bookmarks) -> Result<Option<Movie>> {
+        let mut txn = self.read_txn(bookmarks).await?;
+        let mut rows = self.execute(&mut txn, "movie", q).await?;
 
-        if let Some(row) = rows.next().await? {
+        if let Some(row) = rows.next(&mut txn).await? {
             let movie = Movie {
                 released: row.get::<Option<i64>>("released")?.map(|v| v as u32),
                 title: row.get::<Option<String>>("title")?,
@@ -402,162 +923,126 @@ impl Service {
                     if people.is_empty() { None } else { Some(people) }
                 },
             };
-            let summary = rows.finish().await?;
-            debug!(?summary, ?movie, "movie fetched");
+            rows.finish(&mut txn).await?;
+            txn.commit().await?;
+            debug!(?movie, "movie fetched");
             Ok(Some(movie))
         } else {
+            txn.commit().await?;
             Ok(None)
         }
     }
 
     /// Incrementa y devuelve el total de votos actual del filme.
-    #[instrument(skip(self))]
-    async fn vote(&self, title: String) -> Result<VoteResult> {
+    ///
+    /// Sin `idempotency_key` se ejecuta una sola vez (sin reintentos). Con clave,
+    /// el voto se registra en un nodo `:IdempotencyKey` (con el título votado) y los
+    /// reintentos no lo duplican; la misma clave con otro título es un `KeyReused` (409).
+    /// Devuelve también el bookmark de la escritura para las lecturas posteriores.
+    #[instrument(skip(self, bookmarks), fields(tenant = %self.tenant))]
+    async fn vote(
+        &self,
+        title: String,
+        idempotency_key: Option<String>,
+        bookmarks: &Bookmarks,
+    ) -> Result<(VoteResult, Option<String>)> {
         const VOTE_IN_MOVIE: &str = r#"
             MATCH (movie:Movie {title:$title})
             SET movie.votes = coalesce(movie.votes, 0) + 1
             RETURN movie.votes AS votes
         "#;
+        const VOTE_IN_MOVIE_IDEMPOTENT: &str = r#"
+            MATCH (movie:Movie {title:$title})
+            MERGE (k:IdempotencyKey {key:$key})
+            ON CREATE SET k.created_at = timestamp(),
+                          k.title = $title,
+                          movie.votes = coalesce(movie.votes, 0) + 1
+            RETURN movie.votes AS votes, k.title AS key_title
+        "#;
 
-        let mut rows = self
-            .db
-            .execute(neo4rs::query(VOTE_IN_MOVIE).param("title", title))
-            .await?;
+        let cancel = Cancellable::new();
+        let (q, keyed_title) = match idempotency_key.as_deref() {
+            Some(key) => {
+                (cancel.query(VOTE_IN_MOVIE_IDEMPOTENT).param("title", title.as_str()).param("key", key), Some(title))
+            }
+            None => (cancel.query(VOTE_IN_MOVIE).param("title", title), None),
+        };
 
-        let votes: i64 = rows.single().await?.get("votes")?;
-        Ok(VoteResult { votes: votes as u64 })
+        self.guarded_write("vote", idempotency_key.as_deref(), &cancel, || {
+            self.run_vote(q.clone(), keyed_title.as_deref(), bookmarks)
+        })
+        .await
     }
 
-    /// Búsqueda con paginación básica (offset/limit)
-    #[instrument(skip(self))]
-    async fn search(&self, search: Search) -> Result<Vec<MovieResult>> {
-        const SEARCH_MOVIES: &str = r#"
-          MATCH (movie:Movie)
-          WHERE toLower(movie.title) CONTAINS toLower($part)
-          RETURN movie
-          SKIP $offset LIMIT $limit
-        "#;
+    /// Transacción WRITE explícita (al líder): `Graph::execute` reintenta por su
+    /// cuenta y no debe hacerlo con mutaciones no idempotentes.
+    async fn run_vote(
+        &self,
+        q: neo4rs::Query,
+        keyed_title: Option<&str>,
+        bookmarks: &Bookmarks,
+    ) -> Result<(VoteResult, Option<String>)> {
+        let mut txn = self.db.start_txn_as(Operation::Write, bookmarks.to_option()).await?;
+        let mut rows = self.execute(&mut txn, "vote", q).await?;
+        let row = rows.single(&mut txn).await?;
+        if let Some(title) = keyed_title {
+            // Las claves anteriores a guardar el título no lo llevan: se aceptan
+            let used_for: Option<String> = row.get("key_title")?;
+            if used_for.is_some_and(|t| t != title) {
+                txn.rollback().await?;
+                return Err(KeyReused.into());
+            }
+        }
+        let votes: i64 = row.get("votes")?;
+        let bookmark = txn.commit().await?;
+        Ok((VoteResult { votes: votes as u64 }, bookmark))
+    }
 
-        let limit = search.limit.unwrap_or(25).clamp(1, 200);
-        let offset = search.offset.unwrap_or(0).max(0);
-
-        let mut rows = self
-            .db
-            .execute(
-                neo4rs::query(SEARCH_MOVIES)
-                    .param("part", search.q)
-                    .param("offset", offset as i64)
-                    .param("limit", limit as i64),
-            )
+    /// Búsqueda con paginación básica (offset/limit)
+    #[instrument(skip(self, bookmarks), fields(tenant = %self.tenant))]
+    async fn search(&self, search: Search, bookmarks: &Bookmarks) -> Result<Vec<MovieResult>> {
+        let cancel = Cancellable::new();
+        let q = search_query(&cancel, search);
+
+        let movies = self
+            .guarded_read("search", self.timeouts.search, &cancel, || async {
+                let mut txn = self.read_txn(bookmarks).await?;
+                let mut rows = self.execute(&mut txn, "search", q.clone()).await?;
+                let mut movies = Vec::new();
+                while let Some(row) = rows.next(&mut txn).await? {
+                    movies.push(row.to::<MovieResult>()?);
+                }
+                txn.commit().await?;
+                Ok(movies)
+            })
             .await?;
-
-        let movies: Vec<MovieResult> = rows.into_stream_as::<MovieResult>().try_collect().await?;
         debug!(count = movies.len(), "search results");
         Ok(movies)
     }
 
     /// Grafo con filtros de servidor: tipos de relación, profundidad, etiquetas y año de estreno.
-    #[instrument(skip(self))]
-    async fn graph(&self, browse: Browse) -> Result<BrowseResponse> {
-        let limit = browse.limit.unwrap_or(200).clamp(1, 1000) as i64;
-
-        // Normaliza lista de relaciones a MAYÚSCULAS
-        let rels: Vec<String> = browse
-            .rel
-            .as_deref()
-            .unwrap_or("")
-            .split(',')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_ascii_uppercase())
-            .collect();
-
-        // Etiquetas de nodo
-        let node_incl: Vec<String> = browse
-            .node_incl
-            .as_deref()
-            .unwrap_or("")
-            .split(',')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect();
-
-        let node_excl: Vec<String> = browse
-            .node_excl
-            .as_deref()
-            .unwrap_or("")
-            .split(',')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect();
-
-        // Raíz + profundidad
-        let use_root = browse.root.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty());
-        let depth = browse.depth.unwrap_or(0).clamp(0, 6) as i64;
-
-        // Filtros de año
-        let released_gte: Option<i64> = browse.released_gte;
-        let released_lte: Option<i64> = browse.released_lte;
-
-        // Construcción de Cypher (dos variantes) + properties()
-        let cypher = if use_root.is_some() && depth >= 1 {
-            r#"
-                MATCH (root)
-                WHERE (root:Movie AND root.title = $root)
-                   OR (root:Person AND root.name  = $root)
-                   OR (root:node {title:$root})
-                MATCH p = (root)-[r*1..$depth]-(n)
-                UNWIND relationships(p) AS relx
-                WITH DISTINCT startNode(relx) AS s, endNode(relx) AS t, type(relx) AS rel
-                WHERE (size($rels) = 0 OR rel IN $rels)
-                  AND (size($node_incl) = 0 OR any(lbl IN labels(s) WHERE lbl IN $node_incl))
-                  AND (size($node_incl) = 0 OR any(lbl IN labels(t) WHERE lbl IN $node_incl))
-                  AND (size($node_excl) = 0 OR all(lbl IN labels(s) WHERE NOT lbl IN $node_excl))
-                  AND (size($node_excl) = 0 OR all(lbl IN labels(t) WHERE NOT lbl IN $node_excl))
-                  AND ($released_gte IS NULL OR CASE WHEN s:Movie THEN coalesce(s.released,-1) >= $released_gte ELSE true END)
-                  AND ($released_gte IS NULL OR CASE WHEN t:Movie THEN coalesce(t.released,-1) >= $released_gte ELSE true END)
-                  AND ($released_lte IS NULL OR CASE WHEN s:Movie THEN coalesce(s.released,999999) <= $released_lte ELSE true END)
-                  AND ($released_lte IS NULL OR CASE WHEN t:Movie THEN coalesce(t.released,999999) <= $released_lte ELSE true END)
-                RETURN s, t, rel, properties(s) AS sProps, properties(t) AS tProps
-                LIMIT $limit
-            "#
+    #[instrument(skip(self, bookmarks), fields(tenant = %self.tenant))]
+    async fn graph(&self, browse: Browse, bookmarks: &Bookmarks) -> Result<BrowseResponse> {
+        let GraphQuery { cancel, query: q, is_path } = graph_query(browse, 1000);
+        let (operation, timeout) = if is_path {
+            ("graph_path", self.timeouts.path)
         } else {
-            r#"
-                MATCH (s)-[r]->(t)
-                WHERE (size($rels) = 0 OR type(r) IN $rels)
-                  AND (size($node_incl) = 0 OR any(lbl IN labels(s) WHERE lbl IN $node_incl))
-                  AND (size($node_incl) = 0 OR any(lbl IN labels(t) WHERE lbl IN $node_incl))
-                  AND (size($node_excl) = 0 OR all(lbl IN labels(s) WHERE NOT lbl IN $node_excl))
-                  AND (size($node_excl) = 0 OR all(lbl IN labels(t) WHERE NOT lbl IN $node_excl))
-                  AND ($released_gte IS NULL OR CASE WHEN s:Movie THEN coalesce(s.released,-1) >= $released_gte ELSE true END)
-                  AND ($released_gte IS NULL OR CASE WHEN t:Movie THEN coalesce(t.released,-1) >= $released_gte ELSE true END)
-                  AND ($released_lte IS NULL OR CASE WHEN s:Movie THEN coalesce(s.released,999999) <= $released_lte ELSE true END)
-                  AND ($released_lte IS NULL OR CASE WHEN t:Movie THEN coalesce(t.released,999999) <= $released_lte ELSE true END)
-                RETURN s, t, type(r) AS rel, properties(s) AS sProps, properties(t) AS tProps
-                LIMIT $limit
-            "#
+            ("graph", self.timeouts.graph)
         };
+        self.guarded_read(operation, timeout, &cancel, || self.collect_graph(operation, q.clone(), bookmarks))
+            .await
+    }
 
-        let mut rows = self.db.execute(
-            query(cypher)
-                .param("root", use_root.unwrap_or_default())
-                .param("depth", if depth >= 1 { depth } else { 1 })
-                .param("rels", rels.clone())
-                .param("node_incl", node_incl.clone())
-                .param("node_excl", node_excl.clone())
-                .param("released_gte", released_gte)
-                .param("released_lte", released_lte)
-                .param("limit", limit),
-        ).await?;
+    async fn collect_graph(&self, name: &'static str, q: neo4rs::Query, bookmarks: &Bookmarks) -> Result<BrowseResponse> {
+        let mut txn = self.read_txn(bookmarks).await?;
+        let mut rows = self.execute(&mut txn, name, q).await?;
 
         // Índices para arrays compactos
         let mut index: HashMap<String, usize> = HashMap::new();
         let mut nodes: Vec<Node> = Vec::new();
         let mut links: Vec<Link> = Vec::new();
 
-        while let Some(row) = rows.next().await? {
+        while let Some(row) = rows.next(&mut txn).await? {
             let s: NeoNode = row.get("s")?;
             let t: NeoNode = row.get("t")?;
             let rel: String = row.get("rel")?;
@@ -581,9 +1066,203 @@ impl Service {
 
             links.push(Link { source: s_idx, target: t_idx, rel });
         }
+        txn.commit().await?;
 
         Ok(BrowseResponse { nodes, links })
     }
+
+    /// `/graph` en streaming (NDJSON): cada fila de Bolt se escribe en cuanto llega.
+    ///
+    /// Los fallos hasta tener la primera tanda de filas (breaker abierto, pool agotado,
+    /// consulta inválida) son respuestas HTTP normales; después sólo queda cortar la
+    /// respuesta, que se queda sin el registro `end`.
+    async fn graph_stream(self, browse: Browse, bookmarks: Bookmarks) -> Result<Response> {
+        let (tx, rx) = mpsc::channel::<Bytes>(STREAM_CHANNEL_CHUNKS);
+        self.spawn_graph_stream(browse, bookmarks, NdjsonSink { tx, buf: Vec::with_capacity(STREAM_CHUNK_BYTES) })
+            .await?;
+        let headers = [(CONTENT_TYPE, GraphFormat::Ndjson.media_type()), (VARY, "accept")];
+        Ok((headers, Body::from_stream(rx.map(Ok::<_, Infallible>))).into_response())
+    }
+
+    /// Lanza el volcado en streaming hacia `sink` y espera a que la consulta arranque:
+    /// los errores hasta entonces se devuelven aquí; los posteriores van a `GraphSink::abort`.
+    async fn spawn_graph_stream<S: GraphSink + 'static>(self, browse: Browse, bookmarks: Bookmarks, mut sink: S) -> Result<()> {
+        let GraphQuery { cancel, query, .. } = graph_query(browse, STREAM_MAX_LIMIT);
+        let (opened_tx, opened_rx) = oneshot::channel::<Result<()>>();
+
+        tokio::spawn(async move {
+            let mut opened = Some(opened_tx);
+            let stream = self.stream_graph(query, &bookmarks, &mut opened, &mut sink);
+            let res = self
+                .pool
+                .run(cancel.run(&self.db, &self.tenant, "graph_stream", Operation::Read, self.timeouts.stream, stream))
+                .await;
+            match (res, opened.take()) {
+                (Err(e), Some(opened)) => {
+                    let _ = opened.send(Err(e));
+                }
+                (Err(e), None) => {
+                    error!(tenant = %self.tenant, error=?e, "graph stream aborted; the response is truncated");
+                    sink.abort(e).await;
+                }
+                (Ok(stats), _) => debug!(tenant = %self.tenant, nodes = stats.nodes, links = stats.links, "graph streamed"),
+            }
+        }.in_current_span());
+
+        opened_rx.await.map_err(|_| eyre!("graph stream task ended before the query started"))?
+    }
+
+    async fn stream_graph<S: GraphSink>(
+        &self,
+        q: neo4rs::Query,
+        bookmarks: &Bookmarks,
+        opened: &mut Option<oneshot::Sender<Result<()>>>,
+        sink: &mut S,
+    ) -> Result<GraphStats> {
+        // Breaker y reintentos sólo cubren la apertura: un stream largo no es una llamada lenta
+        let open = retry::read(&self.retry, &self.tenant, "graph_stream", || async {
+            let mut txn = self.read_txn(bookmarks).await?;
+            let rows = self.execute(&mut txn, "graph_stream", q.clone()).await?;
+            Ok((txn, rows))
+        });
+        let (mut txn, mut rows) = self.breaker.call("graph_stream", open).await?;
+        if let Some(opened) = opened.take() {
+            let _ = opened.send(Ok(()));
+        }
+
+        // Nodos ya emitidos: sólo un hash de su clave y su id (la memoria no crece con las propiedades)
+        let hasher = RandomState::new();
+        let mut index: HashMap<u64, usize> = HashMap::new();
+        let mut stats = GraphStats::default();
+
+        while let Some(row) = rows.next(&mut txn).await? {
+            let s: NeoNode = row.get("s")?;
+            let t: NeoNode = row.get("t")?;
+            let rel: String = row.get("rel")?;
+
+            let mut ids = [0; 2];
+            let mut connected = true;
+            for (id, (node, props)) in ids.iter_mut().zip([(&s, "sProps"), (&t, "tProps")]) {
+                let (key, label, title) = extract_key_label_title(node)?;
+                *id = match index.entry(hasher.hash_one(&key)) {
+                    Entry::Occupied(e) => *e.get(),
+                    Entry::Vacant(e) => {
+                        let node = Node { title, label: label.to_string(), props: row.get(props)? };
+                        connected &= sink.record(GraphRecord::Node { id: stats.nodes, node: &node }).await;
+                        stats.nodes += 1;
+                        *e.insert(stats.nodes - 1)
+                    }
+                };
+            }
+            connected &= sink.record(GraphRecord::Link(&Link { source: ids[0], target: ids[1], rel })).await;
+            stats.links += 1;
+
+            if !connected {
+                debug!(tenant = %self.tenant, "graph stream client disconnected");
+                txn.rollback().await?;
+                return Ok(stats);
+            }
+        }
+        txn.commit().await?;
+
+        sink.record(GraphRecord::End { nodes: stats.nodes, links: stats.links }).await;
+        sink.flush().await;
+        Ok(stats)
+    }
+}
+
+/// Tamaño de cada trozo del cuerpo en streaming y trozos en vuelo (memoria acotada).
+const STREAM_CHUNK_BYTES: usize = 32 * 1024;
+const STREAM_CHANNEL_CHUNKS: usize = 8;
+
+/// Destino de los registros de `/graph` en streaming (NDJSON por HTTP, mensajes gRPC).
+trait GraphSink: Send {
+    /// Emite un registro; `false` si el cliente ya no está.
+    fn record(&mut self, record: GraphRecord<'_>) -> impl Future<Output = bool> + Send;
+    /// Envía lo que quede pendiente al terminar.
+    fn flush(&mut self) -> impl Future<Output = ()> + Send;
+    /// La consulta falló después de empezar a emitir.
+    fn abort(&mut self, err: Report) -> impl Future<Output = ()> + Send;
+}
+
+/// NDJSON en trozos de `STREAM_CHUNK_BYTES` hacia el cuerpo de la respuesta.
+struct NdjsonSink {
+    tx: mpsc::Sender<Bytes>,
+    buf: Vec<u8>,
+}
+
+impl GraphSink for NdjsonSink {
+    async fn record(&mut self, record: GraphRecord<'_>) -> bool {
+        record.write_line(&mut self.buf);
+        self.buf.len() < STREAM_CHUNK_BYTES || self.tx.send(Bytes::from(std::mem::take(&mut self.buf))).await.is_ok()
+    }
+
+    async fn flush(&mut self) {
+        let _ = self.tx.send(Bytes::from(std::mem::take(&mut self.buf))).await;
+    }
+
+    /// Sin el registro `end` el cliente sabe que la respuesta está truncada.
+    async fn abort(&mut self, _err: Report) {}
+}
+
+#[derive(Debug, Default)]
+struct GraphStats {
+    nodes: usize,
+    links: usize,
+}
+
+/// Detalle de película con su reparto (`/movie/:title`).
+fn movie_query(cancel: &Cancellable, title: String) -> neo4rs::Query {
+    const FIND_MOVIE: &str = r#"
+        MATCH (movie:Movie {title:$title})
+        OPTIONAL MATCH (movie)<-[r]-(person:Person)
+        WITH movie.title AS title,
+             movie.tagline AS tagline,
+             movie.released AS released,
+             movie.votes AS votes,
+             collect({
+                name: person.name,
+                job: head(split(toLower(type(r)),'_')),
+                role: r.roles
+             }) AS cast
+        RETURN title, tagline, released, votes, cast
+        LIMIT 1
+    "#;
+    cancel.query(FIND_MOVIE).param("title", title)
+}
+
+/// Búsqueda con paginación básica (offset/limit).
+fn search_query(cancel: &Cancellable, search: Search) -> neo4rs::Query {
+    const SEARCH_MOVIES: &str = r#"
+      MATCH (movie:Movie)
+      WHERE toLower(movie.title) CONTAINS toLower($part)
+      RETURN movie
+      SKIP $offset LIMIT $limit
+    "#;
+
+    let limit = search.limit.unwrap_or(25).clamp(1, 200);
+    let offset = search.offset.unwrap_or(0).max(0);
+    cancel
+        .query(SEARCH_MOVIES)
+        .param("part", search.q)
+        .param("offset", offset)
+        .param("limit", limit)
+}
+
+/// Consulta de `/graph` ya parametrizada.
+struct GraphQuery {
+    cancel: Cancellable,
+    query: neo4rs::Query,
+    /// `root` + `depth`: caminos de longitud variable (otro timeout)
+    is_path: bool,
+}
+
+/// Consulta de `/graph` (ver `cypher::graph`); `max_limit` depende del modo.
+fn graph_query(browse: Browse, max_limit: i32) -> GraphQuery {
+    let cypher::GraphCypher { cypher, is_path } = cypher::graph(&browse, max_limit);
+    let cancel = Cancellable::new();
+    let query = cypher.query(&cancel);
+    GraphQuery { cancel, query, is_path }
 }
 
 /// Extrae clave única, etiqueta y título visible de un Neo4j Node
@@ -593,10 +1272,10 @@ impl Service {
 fn extract_key_label_title(n: &NeoNode) -> Result<(String, &'static str, String)> {
     let labels = n.labels();
 
-    if labels.iter().any(|&l| l == "Movie") {
+    if labels.contains(&"Movie") {
         let title: String = n.get("title").unwrap_or_else(|_| format!("#{}", n.id()));
         Ok((format!("movie::{}", title), "movie", title))
-    } else if labels.iter().any(|&l| l == "Person") {
+    } else if labels.contains(&"Person") {
         let name: String = n.get("name").unwrap_or_else(|_| format!("#{}", n.id()));
         Ok((format!("person::{}", name), "person", name))
     } else {
@@ -669,6 +1348,7 @@ struct Search {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, IntoParams)]
 #[into_params(parameter_in = Query)]
 struct Browse {
+    /// Máximo de relaciones (1..1000; hasta 100000 en streaming NDJSON)
     limit: Option<i32>,
 
     /// CSV de tipos de relación (ACTED_IN,DIRECTED,...) — si vacío, todos
@@ -726,7 +1406,9 @@ impl IntoResponse for AppError {
             "status": status.as_u16(),
             "error_id": id.to_string(),
         });
-        (status, axum::Json(body)).into_response()
+        let mut response = (status, axum::Json(body)).into_response();
+        response.extensions_mut().insert(ErrorId(id));
+        response
     }
 }
 
@@ -737,7 +1419,18 @@ where
     fn from(err: E) -> Self {
         let inner = err.into();
         debug!(error=?inner, "request error");
-        Self::new(inner, StatusCode::INTERNAL_SERVER_ERROR)
+        let status = if inner.is::<CircuitOpen>() || inner.is::<PoolTimeout>() {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else if inner.is::<QueryTimeout>() {
+            StatusCode::GATEWAY_TIMEOUT
+        } else if inner.is::<dump::NotEmpty>() || inner.is::<KeyReused>() {
+            StatusCode::CONFLICT
+        } else if inner.is::<dump::InvalidDump>() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        Self::new(inner, status)
     }
 }
 
@@ -753,5 +1446,23 @@ fn sanitize_title(title: String) -> Result<String, AppError> {
     Ok(t.to_string())
 }
 
+/// La clave de idempotencia ya se usó para votar otra película (409).
+#[derive(Debug)]
+struct KeyReused;
 
+impl std::fmt::Display for KeyReused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("idempotency key already used for another movie")
+    }
+}
+
+impl std::error::Error for KeyReused {}
 
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, AppError> {
+    let Some(value) = headers.get("idempotency-key") else { return Ok(None) };
+    let key = value.to_str().map(str::trim).unwrap_or_default();
+    if key.is_empty() || key.len() > 200 {
+        return Err(AppError::new(eyre!("invalid idempotency key"), StatusCode::BAD_REQUEST));
+    }
+    Ok(Some(key.to_string()))
+}
@@ -0,0 +1,340 @@
+// ============================
+// Migraciones de datos versionadas
+// ============================
+//
+// Migraciones numeradas (Cypher en `migrations/*.cypher` o funciones Rust) que se
+// aplican en orden. Cada versión aplicada queda en un nodo `:__Migration` de la base
+// de datos del tenant. Un nodo `:__MigrationLock` con dueño y caducidad hace de
+// cerrojo: sólo una réplica migra, las demás esperan y encuentran todo aplicado.
+// Mientras migra, la réplica renueva la caducidad; si aun así lo pierde, aborta.
+//
+// Las sentencias Cypher se ejecutan en transacciones implícitas (admiten
+// `CALL { … } IN TRANSACTIONS`) y el registro se escribe al terminar, así que una
+// migración interrumpida se repite entera: deben ser idempotentes.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr as _};
+use futures::future::BoxFuture;
+use neo4rs::Graph;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    console::{self, Opaque},
+    schema::{self, SchemaItem},
+};
+
+type RustStep = for<'a> fn(&'a Graph) -> BoxFuture<'a, Result<()>>;
+
+#[derive(Clone, Copy)]
+pub enum Step {
+    /// Una o varias sentencias separadas por `;`
+    Cypher(&'static str),
+    Rust(RustStep),
+}
+
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub step: Step,
+}
+
+/// Migraciones conocidas, en orden estricto de versión. Nunca se renumeran ni se
+/// editan una vez publicadas: los cambios van en una versión nueva.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "backfill_votes",
+        step: Step::Cypher(include_str!("../migrations/0001_backfill_votes.cypher")),
+    },
+    Migration { version: 2, name: "votes_to_integer", step: Step::Rust(votes_to_integer) },
+];
+
+/// Restricciones de la propia contabilidad de migraciones (también en `schema::REQUIRED`).
+pub const BOOKKEEPING: &[SchemaItem] = &[schema::MIGRATION_VERSION_UNIQUE, schema::MIGRATION_LOCK_UNIQUE];
+
+#[derive(Debug, Clone, Copy)]
+pub struct LockConfig {
+    /// Caducidad del cerrojo (por si la réplica que migra muere a mitad)
+    pub ttl: Duration,
+    /// Espera máxima mientras otra réplica migra
+    pub wait: Duration,
+}
+
+/// Versión registrada en la base de datos.
+#[derive(Debug, Clone)]
+pub struct Applied {
+    pub name: String,
+    pub applied_at: String,
+}
+
+/// Versiones aplicadas en la base de datos (por número de versión).
+pub async fn applied(db: &Graph) -> Result<BTreeMap<u32, Applied>> {
+    const APPLIED: &str = r#"
+        MATCH (m:__Migration)
+        RETURN m.version AS version, m.name AS name, toString(m.applied_at) AS applied_at
+        ORDER BY version
+    "#;
+
+    let mut rows = db.execute(neo4rs::query(APPLIED)).await?;
+    let mut applied = BTreeMap::new();
+    while let Some(row) = rows.next().await? {
+        let version: i64 = row.get("version")?;
+        applied.insert(
+            version as u32,
+            Applied {
+                name: row.get::<Option<String>>("name")?.unwrap_or_default(),
+                applied_at: row.get::<Option<String>>("applied_at")?.unwrap_or_default(),
+            },
+        );
+    }
+    Ok(applied)
+}
+
+/// Migraciones de `MIGRATIONS` aún no aplicadas.
+pub fn pending(applied: &BTreeMap<u32, Applied>) -> Vec<Migration> {
+    MIGRATIONS.iter().filter(|m| !applied.contains_key(&m.version)).copied().collect()
+}
+
+/// Aplica las migraciones pendientes bajo el cerrojo. Devuelve las aplicadas.
+pub async fn up(db: &Graph, tenant: &str, lock: LockConfig) -> Result<Vec<Migration>> {
+    for item in BOOKKEEPING {
+        db.run(neo4rs::query(&item.create())).await?;
+    }
+
+    let owner = format!("{}:{}", std::env::var("HOSTNAME").unwrap_or_else(|_| "local".into()), Uuid::new_v4());
+    acquire(db, tenant, &owner, lock).await?;
+    // Si se pierde el cerrojo se abandona la migración en curso (sin registrarla): otra
+    // réplica lo ha tomado y la repetirá entera
+    let res = tokio::select! {
+        res = run_pending(db, tenant, &owner) => res,
+        Err(e) = renew(db, tenant, &owner, lock) => Err(e),
+    };
+    if let Err(e) = release(db, &owner).await {
+        warn!(tenant, error=?e, "failed to release migration lock (it expires on its own)");
+    }
+    res
+}
+
+async fn run_pending(db: &Graph, tenant: &str, owner: &str) -> Result<Vec<Migration>> {
+    // Se relee con el cerrojo tomado: otra réplica puede haber migrado mientras esperábamos
+    let pending = pending(&applied(db).await?);
+    for m in &pending {
+        let started = Instant::now();
+        let res = async {
+            match m.step {
+                Step::Cypher(cypher) => {
+                    for statement in statements(cypher)? {
+                        db.run(neo4rs::query(statement)).await?;
+                    }
+                    Ok(())
+                }
+                Step::Rust(f) => f(db).await,
+            }
+        };
+        res.await.wrap_err_with(|| format!("migration {:04} {} failed", m.version, m.name))?;
+        record(db, m, owner, started.elapsed()).await?;
+        info!(tenant, version = m.version, name = m.name, elapsed=?started.elapsed(), "migration applied");
+    }
+    Ok(pending)
+}
+
+async fn record(db: &Graph, m: &Migration, owner: &str, elapsed: Duration) -> Result<()> {
+    const RECORD: &str = r#"
+        MERGE (m:__Migration {version:$version})
+        SET m.name = $name, m.applied_at = datetime(), m.applied_by = $owner, m.duration_ms = $duration_ms
+    "#;
+    db.run(
+        neo4rs::query(RECORD)
+            .param("version", m.version as i64)
+            .param("name", m.name)
+            .param("owner", owner)
+            .param("duration_ms", elapsed.as_millis() as i64),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Toma el cerrojo o espera (sondeando) a que la réplica que lo tiene termine.
+async fn acquire(db: &Graph, tenant: &str, owner: &str, lock: LockConfig) -> Result<()> {
+    // El primer SET bloquea el nodo en escritura antes de leer el dueño: dos réplicas
+    // a la vez se serializan y sólo una ve el cerrojo libre.
+    const ACQUIRE: &str = r#"
+        MERGE (l:__MigrationLock {id:'migrations'})
+        SET l.probed_at = timestamp()
+        WITH l, (l.owner IS NULL OR l.owner = $owner OR coalesce(l.expires_at, 0) < timestamp()) AS free
+        FOREACH (_ IN CASE WHEN free THEN [1] ELSE [] END |
+            SET l.owner = $owner, l.expires_at = timestamp() + $ttl_ms)
+        RETURN free AS acquired, l.owner AS holder
+    "#;
+
+    let deadline = Instant::now() + lock.wait;
+    loop {
+        let attempt = async {
+            let mut rows = db
+                .execute(neo4rs::query(ACQUIRE).param("owner", owner).param("ttl_ms", lock.ttl.as_millis() as i64))
+                .await?;
+            let row = rows.single().await?;
+            Ok::<_, color_eyre::Report>((row.get::<bool>("acquired")?, row.get::<Option<String>>("holder")?))
+        };
+        let holder = match attempt.await {
+            Ok((true, _)) => return Ok(()),
+            Ok((false, holder)) => holder.unwrap_or_default(),
+            // P.ej. dos réplicas creando el nodo a la vez: la restricción única hace fallar a una
+            Err(e) => format!("unknown ({e})"),
+        };
+        if Instant::now() >= deadline {
+            bail!("migration lock for tenant `{tenant}` still held by {holder} after {:?}", lock.wait);
+        }
+        info!(tenant, %holder, "waiting for migration lock");
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Renueva la caducidad del cerrojo cada `ttl/3` mientras dura la migración, para que una
+/// migración larga no lo deje caducar. Sólo termina (con error) si el cerrojo ya es de otro.
+async fn renew(db: &Graph, tenant: &str, owner: &str, lock: LockConfig) -> Result<()> {
+    const RENEW: &str = r#"
+        MATCH (l:__MigrationLock {id:'migrations'})
+        WHERE l.owner = $owner
+        SET l.expires_at = timestamp() + $ttl_ms
+        RETURN count(l) AS renewed
+    "#;
+
+    let every = (lock.ttl / 3).max(Duration::from_secs(1));
+    loop {
+        tokio::time::sleep(every).await;
+        let attempt = async {
+            let mut rows = db
+                .execute(neo4rs::query(RENEW).param("owner", owner).param("ttl_ms", lock.ttl.as_millis() as i64))
+                .await?;
+            Ok::<_, color_eyre::Report>(rows.single().await?.get::<i64>("renewed")? > 0)
+        };
+        match attempt.await {
+            Ok(true) => {}
+            Ok(false) => bail!("migration lock for tenant `{tenant}` lost (expired and taken by another replica)"),
+            // Aún quedan dos renovaciones antes de que caduque
+            Err(e) => warn!(tenant, error=?e, "failed to renew migration lock"),
+        }
+    }
+}
+
+async fn release(db: &Graph, owner: &str) -> Result<()> {
+    const RELEASE: &str = r#"
+        MATCH (l:__MigrationLock {id:'migrations'})
+        WHERE l.owner = $owner
+        REMOVE l.owner, l.expires_at
+    "#;
+    db.run(neo4rs::query(RELEASE).param("owner", owner)).await?;
+    Ok(())
+}
+
+/// Sentencias de un fichero `.cypher`, sin las que sólo tienen comentarios. Sólo separa
+/// un `;` fuera de literales, nombres entre comillas invertidas y comentarios: se trocea
+/// el fichero entero antes de ejecutar nada, así que un fichero mal formado no deja la
+/// migración a medias.
+pub fn statements(cypher: &str) -> Result<Vec<&str>> {
+    let bytes = cypher.as_bytes();
+    let mut out = Vec::new();
+    let (mut start, mut code, mut i) = (0, false, 0);
+    while i <= bytes.len() {
+        if i == bytes.len() || bytes[i] == b';' {
+            if code {
+                out.push(cypher[start..i].trim());
+            }
+            (start, code, i) = (i + 1, false, i + 1);
+            continue;
+        }
+        match console::opaque(cypher, i).map_err(|e| eyre!("{e} in migration"))? {
+            Some((kind, end)) => {
+                code |= kind != Opaque::Comment;
+                i = end;
+            }
+            None => {
+                code |= !bytes[i].is_ascii_whitespace();
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+// ============================
+// Migraciones Rust
+// ============================
+
+/// `votes` importados como texto o decimal pasan a entero, por lotes. Se filtra por tipo:
+/// `3.0 = toInteger(3.0)` es cierto y quedaría como decimal. Lo que no es un número
+/// (`'abc'`, listas…) se guarda tal cual en `votes_unparsed` y `votes` pasa a 0; si no,
+/// esas películas volverían a salir en cada lote.
+fn votes_to_integer(db: &Graph) -> BoxFuture<'_, Result<()>> {
+    const BATCH: &str = r#"
+        MATCH (movie:Movie)
+        WHERE movie.votes IS NOT NULL AND valueType(movie.votes) <> 'INTEGER NOT NULL'
+        WITH movie, toIntegerOrNull(movie.votes) AS votes LIMIT $batch
+        FOREACH (_ IN CASE WHEN votes IS NULL THEN [1] ELSE [] END | SET movie.votes_unparsed = movie.votes)
+        SET movie.votes = coalesce(votes, 0)
+        RETURN count(movie) AS updated, count(CASE WHEN votes IS NULL THEN 1 END) AS unparsed
+    "#;
+
+    Box::pin(async move {
+        loop {
+            let mut rows = db.execute(neo4rs::query(BATCH).param("batch", 1000_i64)).await?;
+            let row = rows.single().await?;
+            let updated: i64 = row.get("updated")?;
+            let unparsed: i64 = row.get("unparsed")?;
+            if unparsed > 0 {
+                warn!(unparsed, "non-numeric votes reset to 0; the original value is kept in `votes_unparsed`");
+            }
+            if updated == 0 {
+                return Ok(());
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_split_on_top_level_semicolons() {
+        let cypher = "// cabecera\nMATCH (n) SET n.a = 1;\n\n  MATCH (n) SET n.b = 2  ;\n// nada;\n/* otro */;";
+        assert_eq!(statements(cypher).unwrap(), ["// cabecera\nMATCH (n) SET n.a = 1", "MATCH (n) SET n.b = 2"]);
+    }
+
+    #[test]
+    fn statements_keep_semicolons_in_literals_and_names() {
+        let cypher = r#"MATCH (m:Movie) SET m.tagline = 'a; b', m.note = "c;\"d"; MATCH (n) RETURN n.`x;y`"#;
+        assert_eq!(
+            statements(cypher).unwrap(),
+            [r#"MATCH (m:Movie) SET m.tagline = 'a; b', m.note = "c;\"d""#, "MATCH (n) RETURN n.`x;y`"]
+        );
+    }
+
+    #[test]
+    fn statements_keep_semicolons_in_comments() {
+        let cypher = "MATCH (n) // foo; bar\nSET n.a = 1 /* x; y */;\nRETURN 1";
+        assert_eq!(statements(cypher).unwrap(), ["MATCH (n) // foo; bar\nSET n.a = 1 /* x; y */", "RETURN 1"]);
+    }
+
+    #[test]
+    fn statements_reject_unterminated_literals() {
+        assert!(statements("SET n.a = 'oops; RETURN 1").is_err());
+        assert!(statements("RETURN n.`oops; RETURN 1").is_err());
+    }
+
+    #[test]
+    fn published_migrations_split() {
+        for m in MIGRATIONS {
+            if let Step::Cypher(cypher) = m.step {
+                assert!(!statements(cypher).unwrap().is_empty(), "{:04} {}", m.version, m.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+// ============================
+// Planes de ejecución (?explain=true / ?profile=true)
+// ============================
+//
+// `/search`, `/graph` y `/movie/:title` aceptan `explain=true` o `profile=true` con el
+// token de administración. La respuesta normal va en `result` y, junto a ella, el plan
+// de la MISMA consulta (mismo texto y parámetros) ejecutada con `EXPLAIN` (sólo plan
+// y estimaciones) o `PROFILE` (además filas y db hits reales por operador), más los
+// escaneos que filtran por propiedad sin índice.
+//
+// neo4rs recibe el plan en el resumen de la consulta pero no lo expone (el campo es
+// `pub(crate)`); sí implementa `Debug`, así que se lee de ahí con un analizador
+// acotado a los valores que Neo4j pone en un plan (mapas, listas, textos y números).
+// Si una versión de neo4rs cambiara ese formato, `plan` sale vacío y se avisa en el log;
+// por eso la versión va fijada en `Cargo.toml` y los tests usan el `Debug` real de un
+// `ResultSummary` (construido con su `Deserialize`, como lo hace el driver).
+
+use std::time::Duration;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use color_eyre::eyre::{bail, eyre, Result};
+use neo4rs::summary::ResultSummary;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    admin::Admin,
+    bookmarks::Bookmarks,
+    codec::{Codec, Encoded},
+    deadline::Cancellable,
+    tenant::Tenants,
+    AppError, Service,
+};
+
+/// Parámetros de depuración (sólo administradores).
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PlanQuery {
+    /// Añade el plan de la consulta (`EXPLAIN`, sin ejecutarla otra vez). Requiere `ADMIN_TOKEN`
+    #[serde(default)]
+    explain: Option<bool>,
+    /// Añade el perfil de la consulta (`PROFILE`: filas y db hits por operador). Requiere `ADMIN_TOKEN`
+    #[serde(default)]
+    profile: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanMode {
+    Explain,
+    Profile,
+}
+
+impl PlanMode {
+    fn keyword(self) -> &'static str {
+        match self {
+            Self::Explain => "EXPLAIN",
+            Self::Profile => "PROFILE",
+        }
+    }
+}
+
+/// `Some(modo)` si la petición pide el plan; exige el token de administración sólo
+/// entonces (`profile` gana a `explain`).
+pub(crate) struct PlanRequest(pub Option<PlanMode>);
+
+#[async_trait]
+impl FromRequestParts<Tenants> for PlanRequest {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, tenants: &Tenants) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PlanQuery>::from_request_parts(parts, tenants)
+            .await
+            .map_err(|e| AppError::new(eyre!("{e}"), StatusCode::BAD_REQUEST))?;
+        let mode = match (query.explain, query.profile) {
+            (_, Some(true)) => PlanMode::Profile,
+            (Some(true), _) => PlanMode::Explain,
+            _ => return Ok(Self(None)),
+        };
+        Admin::from_request_parts(parts, tenants).await?;
+        Ok(Self(Some(mode)))
+    }
+}
+
+impl PlanRequest {
+    /// Plan de la consulta `query` si la petición lo pidió.
+    pub async fn run(
+        &self,
+        service: &Service,
+        operation: &'static str,
+        timeout: Duration,
+        query: impl FnOnce() -> neo4rs::Query,
+        bookmarks: &Bookmarks,
+    ) -> Result<Option<PlanReport>, AppError> {
+        match self.0 {
+            // En el heap: no agranda el futuro de los handlers que no piden el plan
+            Some(mode) => Ok(Some(Box::pin(service.plan(operation, timeout, mode, &query(), bookmarks)).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Respuesta con plan: la normal en `result`.
+#[derive(Debug, Serialize)]
+pub struct WithPlan<T> {
+    result: T,
+    plan: PlanReport,
+}
+
+/// Codifica `result` tal cual o, si hay plan, dentro de `WithPlan`.
+pub fn respond<T: Serialize>(codec: Codec, result: T, plan: Option<PlanReport>) -> Response {
+    match plan {
+        Some(plan) => Encoded(codec, WithPlan { result, plan }).into_response(),
+        None => Encoded(codec, result).into_response(),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanReport {
+    mode: PlanMode,
+    /// Árbol de operadores (raíz = `ProduceResults`)
+    plan: Option<PlanNode>,
+    /// Suma de db hits del árbol (sólo `profile`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_db_hits: Option<i64>,
+    /// Escaneos de nodos seguidos de un filtro por propiedad: candidatos a índice
+    missing_indexes: Vec<MissingIndex>,
+    /// Avisos de Neo4j sobre la consulta (productos cartesianos, patrones sin límite…)
+    notifications: Vec<PlanNotification>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanNode {
+    /// Operador sin el sufijo del runtime (`NodeByLabelScan`, `Expand(All)`…)
+    operator: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+    identifiers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimated_rows: Option<f64>,
+    /// Filas reales (sólo `profile`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows: Option<i64>,
+    /// Accesos al almacén (sólo `profile`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    db_hits: Option<i64>,
+    #[schema(value_type = Vec<Object>)]
+    children: Vec<PlanNode>,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct MissingIndex {
+    /// `NodeByLabelScan` o `AllNodesScan`
+    operator: String,
+    variable: String,
+    /// Etiqueta escaneada (`None` si se recorren todos los nodos)
+    label: Option<String>,
+    property: String,
+    /// Índice que evitaría el escaneo (si hay etiqueta)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanNotification {
+    code: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+}
+
+// ============================
+// Service
+// ============================
+
+impl Service {
+    /// `EXPLAIN`/`PROFILE` de `q` (mismo texto y parámetros) en una transacción READ.
+    async fn plan(
+        &self,
+        operation: &'static str,
+        timeout: Duration,
+        mode: PlanMode,
+        q: &neo4rs::Query,
+        bookmarks: &Bookmarks,
+    ) -> Result<PlanReport> {
+        let cancel = Cancellable::new();
+        let planned = cancel
+            .query(&format!("{} {}", mode.keyword(), q.query()))
+            .with_params(q.get_params().clone());
+        self.guarded_read(operation, timeout, &cancel, || async {
+            let mut txn = self.read_txn(bookmarks).await?;
+            // Con PROFILE, descartar las filas las consume igualmente en el servidor
+            let summary = self.execute(&mut txn, operation, planned.clone()).await?.finish(&mut txn).await?;
+            txn.rollback().await?;
+            Ok(PlanReport::new(mode, &summary))
+        })
+        .await
+    }
+}
+
+impl PlanReport {
+    fn new(mode: PlanMode, summary: &ResultSummary) -> Self {
+        let plan = match summary_plan(&format!("{summary:?}"), mode) {
+            Ok(plan) => plan.as_ref().and_then(PlanNode::from_value),
+            Err(err) => {
+                warn!(error = %err, "could not read the query plan from the result summary");
+                None
+            }
+        };
+        let mut missing_indexes = Vec::new();
+        if let Some(plan) = &plan {
+            plan.scans(&mut missing_indexes);
+        }
+        Self {
+            mode,
+            total_db_hits: plan.as_ref().filter(|_| mode == PlanMode::Profile).map(PlanNode::total_db_hits),
+            plan,
+            missing_indexes,
+            notifications: summary
+                .notifications()
+                .iter()
+                .map(|n| PlanNotification {
+                    code: n.code.clone(),
+                    title: n.title.clone(),
+                    description: n.description.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// ============================
+// Árbol de operadores
+// ============================
+
+const SCANS: &[&str] = &["NodeByLabelScan", "AllNodesScan"];
+
+impl PlanNode {
+    /// Nodo del plan de Bolt: `operatorType`, `args`, `identifiers`, `children` (+ `rows`,
+    /// `dbHits` con PROFILE).
+    fn from_value(value: &Value) -> Option<Self> {
+        let node = value.as_object()?;
+        let args = node.get("args").and_then(Value::as_object);
+        let arg = |key: &str| args.and_then(|a| a.get(key));
+        let operator = node.get("operatorType")?.as_str()?;
+        Some(Self {
+            operator: operator.split('@').next().unwrap_or(operator).to_string(),
+            details: arg("Details").and_then(Value::as_str).map(str::to_string),
+            identifiers: strings(node.get("identifiers")),
+            estimated_rows: arg("EstimatedRows").and_then(Value::as_f64),
+            rows: node.get("rows").and_then(Value::as_i64),
+            db_hits: node.get("dbHits").and_then(Value::as_i64),
+            children: node
+                .get("children")
+                .and_then(Value::as_array)
+                .map(|c| c.iter().filter_map(Self::from_value).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn total_db_hits(&self) -> i64 {
+        self.db_hits.unwrap_or(0) + self.children.iter().map(Self::total_db_hits).sum::<i64>()
+    }
+
+    /// Un `Filter` directamente sobre un escaneo de nodos que compara propiedades de
+    /// la variable escaneada: Neo4j lee todos los nodos (de la etiqueta) y descarta.
+    fn scans(&self, found: &mut Vec<MissingIndex>) {
+        if self.operator == "Filter" {
+            for scan in self.children.iter().filter(|c| SCANS.contains(&c.operator.as_str())) {
+                let Some((variable, label)) = scan.scanned() else { continue };
+                for property in properties(self.details.as_deref().unwrap_or_default(), &variable) {
+                    let index = MissingIndex {
+                        operator: scan.operator.clone(),
+                        suggestion: label
+                            .as_ref()
+                            .map(|l| format!("CREATE INDEX IF NOT EXISTS FOR (n:{l}) ON (n.{property})")),
+                        variable: variable.clone(),
+                        label: label.clone(),
+                        property,
+                    };
+                    if !found.contains(&index) {
+                        found.push(index);
+                    }
+                }
+            }
+        }
+        for child in &self.children {
+            child.scans(found);
+        }
+    }
+
+    /// Variable y etiqueta de un escaneo (`movie:Movie`, o sólo `n` en `AllNodesScan`).
+    fn scanned(&self) -> Option<(String, Option<String>)> {
+        let details = self.details.as_deref().or_else(|| self.identifiers.first().map(String::as_str))?;
+        let (variable, label) = match details.split_once(':') {
+            Some((variable, label)) => (variable, Some(label.trim().trim_matches('`').to_string())),
+            None => (details, None),
+        };
+        Some((variable.trim().trim_matches('`').to_string(), label))
+    }
+}
+
+/// Propiedades `variable.prop` mencionadas en los detalles de un `Filter`.
+fn properties(details: &str, variable: &str) -> Vec<String> {
+    let ident = |c: char| c.is_alphanumeric() || c == '_';
+    let prefix = format!("{variable}.");
+    let mut found: Vec<String> = Vec::new();
+    let mut rest = details;
+    while let Some(at) = rest.find(&prefix) {
+        let preceded = details[..details.len() - rest.len() + at].chars().next_back().is_some_and(ident);
+        rest = &rest[at + prefix.len()..];
+        let property: String = rest.chars().take_while(|c| ident(*c)).collect();
+        if !preceded && !property.is_empty() && !found.contains(&property) {
+            found.push(property);
+        }
+    }
+    found
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|v| v.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// ============================
+// Lectura del plan desde `Debug`
+// ============================
+
+/// Campo `plan` (EXPLAIN) o `profile` (PROFILE) del `Debug` de `ResultSummary`.
+// TODO: pedir en https://github.com/neo4j-labs/neo4rs/issues accesores públicos para
+// `plan`/`profile` de `ResultSummary`; con ellos sobra este analizador y se puede
+// quitar la versión exacta de neo4rs en Cargo.toml.
+fn summary_plan(debug: &str, mode: PlanMode) -> Result<Option<Value>> {
+    // Los campos anteriores (bookmark, tipo, base de datos, contadores) no contienen
+    // `, plan: `; a partir de ahí se analiza en orden
+    let Some(start) = debug.find(", plan: ") else { bail!("no `plan` field") };
+    let mut parser = Parser { input: &debug[start + ", plan: ".len()..] };
+    let plan = parser.option()?;
+    parser.expect(", profile: ")?;
+    let profile = parser.option()?;
+    Ok(match mode {
+        PlanMode::Explain => plan,
+        PlanMode::Profile => profile,
+    })
+}
+
+/// Analizador del `Debug` derivado de `HashMap<String, neo4rs::bolt::Bolt>`.
+struct Parser<'a> {
+    input: &'a str,
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, token: &str) -> bool {
+        match self.input.strip_prefix(token) {
+            Some(rest) => {
+                self.input = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            bail!("expected `{token}` at `{}`", self.input.chars().take(20).collect::<String>())
+        }
+    }
+
+    fn option(&mut self) -> Result<Option<Value>> {
+        if self.eat("None") {
+            return Ok(None);
+        }
+        self.expect("Some(")?;
+        let map = self.map()?;
+        self.expect(")")?;
+        Ok(Some(map))
+    }
+
+    fn map(&mut self) -> Result<Value> {
+        self.expect("{")?;
+        let mut map = Map::new();
+        if !self.eat("}") {
+            loop {
+                let key = self.string()?;
+                self.expect(": ")?;
+                map.insert(key, self.value()?);
+                if self.eat("}") {
+                    break;
+                }
+                self.expect(", ")?;
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        if self.eat("Null") {
+            return Ok(Value::Null);
+        }
+        let value = if self.eat("Boolean(") {
+            Value::Bool(self.scalar()?.parse()?)
+        } else if self.eat("Integer(") {
+            Value::from(self.scalar()?.parse::<i64>()?)
+        } else if self.eat("Float(") {
+            // NaN/inf no caben en JSON: quedan como null
+            Value::from(self.scalar()?.parse::<f64>()?)
+        } else if self.eat("String(") {
+            Value::String(self.string()?)
+        } else if self.eat("List([") {
+            let mut list = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    list.push(self.value()?);
+                    if self.eat("]") {
+                        break;
+                    }
+                    self.expect(", ")?;
+                }
+            }
+            Value::Array(list)
+        } else if self.eat("Dictionary(") {
+            self.map()?
+        } else {
+            bail!("unsupported plan value at `{}`", self.input.chars().take(20).collect::<String>());
+        };
+        self.expect(")")?;
+        Ok(value)
+    }
+
+    /// Texto hasta el `)` que cierra un escalar.
+    fn scalar(&mut self) -> Result<&str> {
+        let end = self.input.find(')').ok_or_else(|| eyre!("unterminated value"))?;
+        let (scalar, rest) = self.input.split_at(end);
+        self.input = rest;
+        Ok(scalar)
+    }
+
+    /// Cadena con los escapes de `str::escape_debug`.
+    fn string(&mut self) -> Result<String> {
+        self.expect("\"")?;
+        let mut out = String::new();
+        let mut chars = self.input.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.input = &self.input[i + 1..];
+                    return Ok(out);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('0') => out.push('\0'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().map(|(_, c)| c).skip(1).take_while(|c| *c != '}').collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16)?).ok_or_else(|| eyre!("bad escape"))?);
+                    }
+                    Some(c) => out.push(c),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        bail!("unterminated string")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use neo4rs::summary::Streaming;
+    use serde::de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    /// JSON legible con cualquier `deserialize_*`: el `Deserialize` de `Bolt` pide
+    /// `deserialize_bytes` (como el formato packstream del driver), que `serde_json` no acepta.
+    struct Any(Value);
+
+    impl<'de> de::Deserializer<'de> for Any {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0 {
+                Value::Null => visitor.visit_unit(),
+                Value::Bool(b) => visitor.visit_bool(b),
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => visitor.visit_i64(i),
+                    None => visitor.visit_f64(n.as_f64().unwrap_or_default()),
+                },
+                Value::String(s) => visitor.visit_string(s),
+                Value::Array(a) => visitor.visit_seq(SeqDeserializer::new(a.into_iter().map(Any))),
+                Value::Object(o) => visitor.visit_map(MapDeserializer::new(o.into_iter().map(|(k, v)| (k, Any(v))))),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    impl IntoDeserializer<'_, de::value::Error> for Any {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    /// `ResultSummary` real (el mismo `Deserialize` que usa el driver con el SUCCESS de Bolt).
+    fn summary(extra: Value) -> ResultSummary {
+        let mut fields = json!({"type": "r", "db": "movies"});
+        fields.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        match Streaming::deserialize(Any(fields)).unwrap() {
+            Streaming::Done(summary) => *summary,
+            Streaming::HasMore => panic!("summary expected"),
+        }
+    }
+
+    /// `MATCH (movie:Movie) WHERE movie.tagline = $tagline RETURN movie`
+    fn explain_plan() -> Value {
+        json!({
+            "operatorType": "ProduceResults@neo4j",
+            "identifiers": ["movie"],
+            "args": {"Details": "movie", "EstimatedRows": 3.8, "PlannerVersion": "5.20"},
+            "children": [{
+                "operatorType": "Filter@neo4j",
+                "identifiers": ["movie"],
+                "args": {"Details": "movie.tagline = $tagline", "EstimatedRows": 3.8},
+                "children": [{
+                    "operatorType": "NodeByLabelScan@neo4j",
+                    "identifiers": ["movie"],
+                    "args": {"Details": "movie:Movie", "EstimatedRows": 38.0},
+                    "children": []
+                }]
+            }]
+        })
+    }
+
+    fn profile_plan() -> Value {
+        json!({
+            "operatorType": "ProduceResults@neo4j",
+            "identifiers": ["p"],
+            "args": {"Details": "p", "EstimatedRows": 1.0},
+            "rows": 1, "dbHits": 0, "pageCacheHits": 0, "pageCacheMisses": 0, "time": 120,
+            "children": [{
+                "operatorType": "NodeUniqueIndexSeek@neo4j",
+                "identifiers": ["p"],
+                "args": {"Details": "UNIQUE p:Person(name) WHERE name = $name", "EstimatedRows": 1.0},
+                "rows": 1, "dbHits": 2, "pageCacheHits": 3, "pageCacheMisses": 0, "time": 80,
+                "children": []
+            }]
+        })
+    }
+
+    #[test]
+    fn reads_explain_plan_from_debug() {
+        let summary = summary(json!({"plan": explain_plan()}));
+        let debug = format!("{summary:?}");
+        assert_eq!(summary_plan(&debug, PlanMode::Explain).unwrap(), Some(explain_plan()));
+        assert_eq!(summary_plan(&debug, PlanMode::Profile).unwrap(), None);
+
+        let report = PlanReport::new(PlanMode::Explain, &summary);
+        let plan = report.plan.unwrap();
+        assert_eq!(plan.operator, "ProduceResults");
+        assert_eq!(plan.children[0].operator, "Filter");
+        assert_eq!(plan.children[0].children[0].estimated_rows, Some(38.0));
+        assert_eq!(report.total_db_hits, None);
+    }
+
+    #[test]
+    fn reads_profile_plan_from_debug() {
+        let summary = summary(json!({"profile": profile_plan()}));
+        assert_eq!(summary_plan(&format!("{summary:?}"), PlanMode::Profile).unwrap(), Some(profile_plan()));
+
+        let report = PlanReport::new(PlanMode::Profile, &summary);
+        let plan = report.plan.unwrap();
+        assert_eq!(plan.children[0].operator, "NodeUniqueIndexSeek");
+        assert_eq!((plan.rows, plan.children[0].db_hits), (Some(1), Some(2)));
+        assert_eq!(report.total_db_hits, Some(2));
+        assert!(report.missing_indexes.is_empty());
+    }
+
+    #[test]
+    fn reads_escaped_strings_and_nested_values() {
+        let details = "m.title = \"Amélie\" AND m.tagline CONTAINS 'a\\b'\n\t✓";
+        let plan = json!({
+            "operatorType": "Filter@neo4j",
+            "args": {"Details": details, "Flags": [true, null, -1, 2.5, {"k": "v"}]},
+            "children": []
+        });
+        let summary = summary(json!({"plan": plan}));
+        assert_eq!(summary_plan(&format!("{summary:?}"), PlanMode::Explain).unwrap(), Some(plan));
+    }
+
+    /// Guarda de la versión exacta de neo4rs: si cambia el `Debug` de `ResultSummary`,
+    /// `summary_plan` deja de encontrar el plan y `/explain` lo perdería en silencio.
+    #[test]
+    fn debug_shape_of_result_summary_is_still_parsed() {
+        let cases = [(PlanMode::Explain, "plan", explain_plan()), (PlanMode::Profile, "profile", profile_plan())];
+        for (mode, field, plan) in cases {
+            let debug = format!("{:?}", summary(json!({ field: plan })));
+            match summary_plan(&debug, mode) {
+                Ok(Some(found)) => assert_eq!(found, plan),
+                other => panic!(
+                    "the Debug output of neo4rs::summary::ResultSummary changed, fix plan::summary_plan \
+                     before bumping neo4rs ({field}: {:?}): {debug}",
+                    other.map_err(|e| e.to_string())
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn summary_without_plan() {
+        let summary = summary(json!({}));
+        assert_eq!(summary_plan(&format!("{summary:?}"), PlanMode::Explain).unwrap(), None);
+        assert!(PlanReport::new(PlanMode::Explain, &summary).plan.is_none());
+        assert!(summary_plan("ResultSummary { bookmark: None }", PlanMode::Explain).is_err());
+    }
+
+    #[test]
+    fn detects_filtered_scans() {
+        let report = PlanReport::new(PlanMode::Explain, &summary(json!({"plan": explain_plan()})));
+        assert_eq!(
+            report.missing_indexes,
+            vec![MissingIndex {
+                operator: "NodeByLabelScan".into(),
+                variable: "movie".into(),
+                label: Some("Movie".into()),
+                property: "tagline".into(),
+                suggestion: Some("CREATE INDEX IF NOT EXISTS FOR (n:Movie) ON (n.tagline)".into()),
+            }]
+        );
+
+        let all_nodes = json!({
+            "operatorType": "Filter@neo4j",
+            "args": {"Details": "n.born > $year AND n.name IS NOT NULL"},
+            "children": [{"operatorType": "AllNodesScan@neo4j", "identifiers": ["n"], "args": {"Details": "n"}, "children": []}]
+        });
+        let report = PlanReport::new(PlanMode::Explain, &summary(json!({"plan": all_nodes})));
+        let found: Vec<_> = report.missing_indexes.iter().map(|m| (m.label.clone(), m.property.as_str())).collect();
+        assert_eq!(found, vec![(None, "born"), (None, "name")]);
+        assert!(report.missing_indexes.iter().all(|m| m.suggestion.is_none()));
+    }
+
+    #[test]
+    fn properties_of_the_scanned_variable_only() {
+        assert_eq!(properties("m.title = $t AND om.other = 1 AND m.released > m.title", "m"), vec!["title", "released"]);
+        assert!(properties("p.name = $name", "m").is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+// ============================
+// Límite de concurrencia hacia Neo4j
+// ============================
+//
+// `neo4rs` no expone el estado de su pool (deadpool) ni un timeout de adquisición.
+// Delante de él ponemos un semáforo del mismo tamaño (`max_connections`): cada
+// llamada guardada de `Service` (`guarded_read`/`guarded_write` y los streams) ocupa un
+// hueco mientras dura, así podemos limitar la espera y exportar uso/espera por tenant.
+//
+// Las métricas (`neo4j_gate_*`) miden este semáforo de la aplicación, NO el pool real
+// del driver: no pasan por él el warmup y `/readyz`, la sincronización de esquema, las
+// migraciones, `/admin/import`, el volcado/restauración, la cancelación de transacciones
+// ni la limpieza de claves de idempotencia, que usan conexiones del pool sin contarse.
+
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// No se obtuvo conexión del pool dentro de `acquire_timeout`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolTimeout(pub Duration);
+
+impl std::fmt::Display for PoolTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timed out after {:?} waiting for a neo4j connection", self.0)
+    }
+}
+
+impl std::error::Error for PoolTimeout {}
+
+#[derive(Debug)]
+pub struct PoolGate {
+    tenant: String,
+    slots: Semaphore,
+    max: usize,
+    waiters: AtomicUsize,
+    acquire_timeout: Duration,
+}
+
+impl PoolGate {
+    pub fn new(tenant: &str, max: usize, acquire_timeout: Duration) -> Self {
+        let gate = Self {
+            tenant: tenant.to_string(),
+            slots: Semaphore::new(max),
+            max,
+            waiters: AtomicUsize::new(0),
+            acquire_timeout,
+        };
+        metrics::gauge!("neo4j_gate_limit", "tenant" => gate.tenant.clone()).set(max as f64);
+        gate.publish();
+        gate
+    }
+
+    /// Ejecuta `fut` ocupando una conexión del pool.
+    pub async fn run<T, F>(&self, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let _slot = self.acquire().await?;
+        fut.await
+    }
+
+    async fn acquire(&self) -> Result<Slot<'_>, PoolTimeout> {
+        let started = Instant::now();
+        let waiting = Waiting::new(self);
+        let permit = tokio::time::timeout(self.acquire_timeout, self.slots.acquire()).await;
+        drop(waiting);
+        metrics::histogram!("neo4j_gate_acquire_seconds", "tenant" => self.tenant.clone()).record(started.elapsed().as_secs_f64());
+
+        match permit {
+            Ok(Ok(permit)) => {
+                self.publish();
+                Ok(Slot { gate: self, permit: Some(permit) })
+            }
+            // El semáforo nunca se cierra; sólo queda el timeout
+            Ok(Err(_)) | Err(_) => {
+                self.publish();
+                metrics::counter!("neo4j_gate_acquire_timeouts_total", "tenant" => self.tenant.clone()).increment(1);
+                Err(PoolTimeout(self.acquire_timeout))
+            }
+        }
+    }
+
+    /// Publica huecos ocupados, libres y esperas actuales del semáforo.
+    fn publish(&self) {
+        let in_use = self.max - self.slots.available_permits();
+        metrics::gauge!("neo4j_gate_in_use", "tenant" => self.tenant.clone()).set(in_use as f64);
+        metrics::gauge!("neo4j_gate_available", "tenant" => self.tenant.clone()).set((self.max - in_use) as f64);
+        metrics::gauge!("neo4j_gate_waiters", "tenant" => self.tenant.clone())
+            .set(self.waiters.load(Ordering::SeqCst) as f64);
+    }
+}
+
+/// Cuenta una espera en curso; se descuenta también si la petición se cancela.
+struct Waiting<'a>(&'a PoolGate);
+
+impl<'a> Waiting<'a> {
+    fn new(gate: &'a PoolGate) -> Self {
+        gate.waiters.fetch_add(1, Ordering::SeqCst);
+        gate.publish();
+        Self(gate)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.waiters.fetch_sub(1, Ordering::SeqCst);
+        self.0.publish();
+    }
+}
+
+struct Slot<'a> {
+    gate: &'a PoolGate,
+    permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        self.gate.publish();
+    }
+}
@@ -0,0 +1,265 @@
+// ============================
+// Registro de consultas (duración, filas, contadores) y slow query log
+// ============================
+//
+// Cada consulta de `Service` pasa por `Service::execute`, que devuelve las filas
+// envueltas en `LoggedRows`: cuenta las filas leídas y, al terminar (o al soltarse
+// por error, timeout o cliente desconectado), registra:
+//   * `neo4j_query_duration_seconds{query,tenant}` (histograma) y
+//     `neo4j_query_rows_total{query,tenant}`: por consulta, no por ruta HTTP;
+//   * un evento `debug` con nombre, huella, duración, filas y contadores del resumen;
+//   * si supera `SLOW_QUERY_MS`, un `warn` "slow query" con el Cypher normalizado y
+//     los parámetros REDACTADOS (sólo nombre y tipo), y `neo4j_slow_queries_total`.
+//
+// La huella (`fingerprint`) es un hash del Cypher sin comentarios ni espacios
+// repetidos: agrupa las ejecuciones de la misma consulta con distintos parámetros.
+//
+// Cada medición abre además un span `neo4j.query` (hijo del span actual) que dura lo
+// mismo que la consulta: con OTLP activo (ver `telemetry.rs`) es el span de cliente
+// con `db.system`/`db.statement` de la traza.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use neo4rs::{summary::ResultSummary, BoltType, Row, RowStream, Txn};
+use tracing::{debug, field, warn, Span};
+
+pub const DURATION_METRIC: &str = "neo4j_query_duration_seconds";
+
+/// Cubos del histograma (segundos): de consultas por índice a recorridos largos.
+pub const DURATION_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Medición de una consulta en curso; registra al soltarse si nadie lo hizo antes.
+pub struct QueryLog {
+    name: &'static str,
+    tenant: Arc<str>,
+    slow: Duration,
+    started: Instant,
+    rows: u64,
+    cypher: String,
+    params: Vec<String>,
+    span: Span,
+    recorded: bool,
+}
+
+impl QueryLog {
+    pub fn start(name: &'static str, tenant: Arc<str>, slow: Duration, q: &neo4rs::Query) -> Self {
+        let mut params: Vec<String> = q
+            .get_params()
+            .value
+            .iter()
+            .map(|(name, value)| format!("{}=<{}>", name.value, kind(value)))
+            .collect();
+        params.sort();
+        let cypher = normalize(q.query());
+        let span = tracing::info_span!(
+            "neo4j.query",
+            otel.name = name,
+            otel.kind = "client",
+            otel.status_code = field::Empty,
+            db.system = "neo4j",
+            db.operation = name,
+            db.statement = %cypher,
+            db.rows = field::Empty,
+            %tenant,
+        );
+        Self {
+            name,
+            tenant,
+            slow,
+            started: Instant::now(),
+            rows: 0,
+            cypher,
+            params,
+            span,
+            recorded: false,
+        }
+    }
+
+    fn record(&mut self, summary: Option<&ResultSummary>) {
+        if std::mem::replace(&mut self.recorded, true) {
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        self.span.record("db.rows", self.rows);
+        if summary.is_none() {
+            self.span.record("otel.status_code", "ERROR");
+        }
+        let labels = [("query", self.name.to_string()), ("tenant", self.tenant.to_string())];
+        metrics::histogram!(DURATION_METRIC, &labels).record(elapsed.as_secs_f64());
+        metrics::counter!("neo4j_query_rows_total", &labels).increment(self.rows);
+
+        let fingerprint = fingerprint(&self.cypher);
+        // Sin resumen: la consulta no llegó al final (error, timeout, cliente desconectado)
+        let counters = summary.map_or_else(|| "incomplete".to_string(), counters);
+        let elapsed_ms = elapsed.as_millis() as u64;
+        if elapsed >= self.slow {
+            metrics::counter!("neo4j_slow_queries_total", &labels).increment(1);
+            warn!(
+                query = self.name,
+                tenant = %self.tenant,
+                %fingerprint,
+                elapsed_ms,
+                rows = self.rows,
+                %counters,
+                params = %self.params.join(" "),
+                cypher = %self.cypher,
+                "slow query"
+            );
+        } else {
+            debug!(query = self.name, tenant = %self.tenant, %fingerprint, elapsed_ms, rows = self.rows, %counters, "query");
+        }
+    }
+}
+
+impl Drop for QueryLog {
+    fn drop(&mut self) {
+        self.record(None);
+    }
+}
+
+/// Filas de una consulta con su medición (ver `Service::execute`).
+pub struct LoggedRows {
+    rows: Option<RowStream>,
+    summary: Option<ResultSummary>,
+    log: QueryLog,
+}
+
+impl LoggedRows {
+    pub fn new(rows: RowStream, log: QueryLog) -> Self {
+        Self { rows: Some(rows), summary: None, log }
+    }
+
+    pub async fn next(&mut self, txn: &mut Txn) -> Result<Option<Row>> {
+        let Some(rows) = self.rows.as_mut() else { return Ok(None) };
+        match rows.next(txn.handle()).await? {
+            Some(row) => {
+                self.log.rows += 1;
+                Ok(Some(row))
+            }
+            None => {
+                // Agotada: el resumen ya llegó con la última página, `finish` no va al servidor
+                if let Some(rows) = self.rows.take() {
+                    let summary = rows.finish(txn.handle()).await?;
+                    self.log.record(Some(&summary));
+                    self.summary = Some(summary);
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Exactamente una fila (como `RowStream::single`).
+    pub async fn single(&mut self, txn: &mut Txn) -> Result<Row> {
+        let row = self.next(txn).await?.ok_or(neo4rs::Error::NoMoreRows)?;
+        if self.next(txn).await?.is_some() {
+            return Err(neo4rs::Error::NotSingleResult.into());
+        }
+        Ok(row)
+    }
+
+    /// Descarta lo que quede y devuelve el resumen.
+    pub async fn finish(mut self, txn: &mut Txn) -> Result<ResultSummary> {
+        let Some(rows) = self.rows.take() else {
+            // Ya agotada por `next`: el resumen se registró entonces
+            return Ok(self.summary.take().expect("flujo agotado sin resumen"));
+        };
+        let summary = rows.finish(txn.handle()).await?;
+        self.log.record(Some(&summary));
+        Ok(summary)
+    }
+}
+
+/// Tipo del parámetro, sin su valor.
+fn kind(value: &BoltType) -> String {
+    match value {
+        BoltType::Null(_) => "null".to_string(),
+        BoltType::String(_) => "string".to_string(),
+        BoltType::Boolean(_) => "boolean".to_string(),
+        BoltType::Integer(_) => "integer".to_string(),
+        BoltType::Float(_) => "float".to_string(),
+        BoltType::List(list) => format!("list[{}]", list.value.len()),
+        BoltType::Map(_) => "map".to_string(),
+        _ => "value".to_string(),
+    }
+}
+
+/// Contadores distintos de cero del resumen (`-` si la consulta no escribió nada).
+fn counters(summary: &ResultSummary) -> String {
+    let s = summary.stats();
+    let counters = [
+        ("nodes_created", s.nodes_created),
+        ("nodes_deleted", s.nodes_deleted),
+        ("relationships_created", s.relationships_created),
+        ("relationships_deleted", s.relationships_deleted),
+        ("properties_set", s.properties_set),
+        ("labels_added", s.labels_added),
+        ("labels_removed", s.labels_removed),
+        ("indexes_added", s.indexes_added),
+        ("indexes_removed", s.indexes_removed),
+        ("constraints_added", s.constraints_added),
+        ("constraints_removed", s.constraints_removed),
+    ];
+    let set: Vec<String> = counters.iter().filter(|(_, n)| *n > 0).map(|(k, n)| format!("{k}={n}")).collect();
+    if set.is_empty() {
+        "-".to_string()
+    } else {
+        set.join(" ")
+    }
+}
+
+/// Cypher sin comentarios `/* … */` (la marca de cancelación cambia en cada consulta)
+/// y con los espacios colapsados.
+fn normalize(cypher: &str) -> String {
+    let mut text = String::with_capacity(cypher.len());
+    let mut rest = cypher;
+    while let Some(start) = rest.find("/*") {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        rest = rest[start..].find("*/").map_or("", |end| &rest[start + end + 2..]);
+    }
+    text.push_str(rest);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// FNV-1a de 64 bits: estable entre ejecuciones y versiones (a diferencia de `DefaultHasher`).
+fn fingerprint(cypher: &str) -> String {
+    let hash = cypher
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3));
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_strips_comments_and_collapses_whitespace() {
+        assert_eq!(
+            normalize("MATCH (m:Movie)\n  WHERE m.title = $t\r\n\tRETURN m"),
+            "MATCH (m:Movie) WHERE m.title = $t RETURN m"
+        );
+        assert_eq!(normalize("/* qid:1 */\nMATCH (n)/*x*/RETURN n /* fin */"), "MATCH (n) RETURN n");
+        // Un comentario sin cerrar se come el resto de la consulta
+        assert_eq!(normalize("RETURN 1 /* sin cerrar RETURN 2"), "RETURN 1");
+        assert_eq!(normalize("  \n "), "");
+    }
+
+    #[test]
+    fn fingerprint_is_fnv1a_64() {
+        assert_eq!(fingerprint(""), "cbf29ce484222325");
+        assert_eq!(fingerprint("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn fingerprint_ignores_the_cancel_tag_and_formatting() {
+        let a = normalize("/* qid:8f14e45f-ceea-467f-a8b4-3c7a9d1e2b10 */\nMATCH (m:Movie {title:$title}) RETURN m");
+        let b = normalize("/* qid:c9f0f895-fb98-4b91-9a3e-5d4f1e2a7c61 */\nMATCH (m:Movie {title:$title})\n  RETURN m");
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&normalize("MATCH (p:Person {name:$title}) RETURN p")));
+    }
+}
@@ -0,0 +1,178 @@
+// ============================
+// Reintentos contra Neo4j
+// ============================
+//
+// Backoff exponencial con "full jitter" para el arranque y para lecturas
+// idempotentes. Las mutaciones sólo se reintentan si llevan clave de idempotencia.
+// Cada reintento se cuenta en `neo4j_retries_total{tenant,operation,reason}`.
+
+use std::{future::Future, time::Duration};
+
+use color_eyre::eyre::{Report, Result};
+use neo4rs::{Neo4jClientErrorKind, Neo4jErrorKind};
+use rand::Rng as _;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Número máximo de intentos (incluido el primero); 0 = sin límite
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Política de un único intento (sin reintentos).
+    pub const fn once() -> Self {
+        Self { max_attempts: 1, initial_backoff: Duration::ZERO, max_backoff: Duration::ZERO }
+    }
+
+    /// Espera antes del reintento `attempt` (1-based): aleatoria en `[0, min(max, initial·2^(attempt-1))]`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let millis = exp.as_millis() as u64;
+        if millis == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::rng().random_range(0..=millis))
+    }
+
+    /// `true` si tras `attempt` intentos ya no quedan más.
+    pub fn exhausted(&self, attempt: u32) -> bool {
+        self.max_attempts != 0 && attempt >= self.max_attempts
+    }
+}
+
+/// Motivo de reintento si el error es transitorio (caída de conexión, cambio de líder…).
+pub fn transient_reason(err: &Report) -> Option<&'static str> {
+    let err = err.downcast_ref::<neo4rs::Error>()?;
+    match err {
+        neo4rs::Error::IOError { .. } => Some("io"),
+        neo4rs::Error::ConnectionError => Some("connection"),
+        neo4rs::Error::ServerUnavailableError(_) => Some("server_unavailable"),
+        neo4rs::Error::RequestIgnoredError => Some("ignored"),
+        neo4rs::Error::Neo4j(e) => match e.kind() {
+            Neo4jErrorKind::Transient => Some("transient"),
+            Neo4jErrorKind::Client(Neo4jClientErrorKind::SessionExpired) => Some("leader_switch"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Ejecuta `op` reintentando errores transitorios según `policy`.
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    tenant: &str,
+    operation: &'static str,
+    mut op: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) => {
+                let Some(reason) = transient_reason(&e) else { return Err(e) };
+                if policy.exhausted(attempt) {
+                    return Err(e);
+                }
+                let delay = policy.delay(attempt);
+                metrics::counter!(
+                    "neo4j_retries_total",
+                    "tenant" => tenant.to_string(),
+                    "operation" => operation,
+                    "reason" => reason
+                )
+                .increment(1);
+                warn!(tenant, operation, attempt, reason, ?delay, error=%e, "transient neo4j error, retrying");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Lecturas: siempre idempotentes, se reintentan.
+pub async fn read<T, F, Fut>(policy: &RetryPolicy, tenant: &str, operation: &'static str, op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    with_retry(policy, tenant, operation, op).await
+}
+
+/// Mutaciones: sólo se reintentan si llevan clave de idempotencia.
+pub async fn write<T, F, Fut>(
+    policy: &RetryPolicy,
+    tenant: &str,
+    operation: &'static str,
+    idempotency_key: Option<&str>,
+    op: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let policy = if idempotency_key.is_some() { *policy } else { RetryPolicy::once() };
+    with_retry(&policy, tenant, operation, op).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+
+    fn policy(initial_ms: u64, max_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(initial_ms),
+            max_backoff: Duration::from_millis(max_ms),
+        }
+    }
+
+    #[test]
+    fn delay_stays_within_the_exponential_cap() {
+        let p = policy(100, 1000);
+        for (attempt, cap) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (40, 1000)] {
+            for _ in 0..50 {
+                assert!(p.delay(attempt) <= Duration::from_millis(cap), "attempt {attempt}");
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_zero_without_backoff() {
+        assert_eq!(RetryPolicy::once().delay(3), Duration::ZERO);
+        assert_eq!(policy(0, 1000).delay(10), Duration::ZERO);
+        assert_eq!(policy(100, 0).delay(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn exhausted_counts_the_first_attempt() {
+        assert!(RetryPolicy::once().exhausted(1));
+        let p = policy(100, 1000);
+        assert!(!p.exhausted(4));
+        assert!(p.exhausted(5));
+        let unlimited = RetryPolicy { max_attempts: 0, ..p };
+        assert!(!unlimited.exhausted(u32::MAX));
+    }
+
+    #[test]
+    fn transient_reasons() {
+        let reason = |e: neo4rs::Error| transient_reason(&e.into());
+        assert_eq!(reason(neo4rs::Error::ConnectionError), Some("connection"));
+        assert_eq!(reason(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into()), Some("io"));
+        assert_eq!(reason(neo4rs::Error::ServerUnavailableError("down".into())), Some("server_unavailable"));
+        assert_eq!(reason(neo4rs::Error::RequestIgnoredError), Some("ignored"));
+        assert_eq!(reason(neo4rs::Error::NotSingleResult), None);
+        assert_eq!(reason(neo4rs::Error::AuthenticationError("bad credentials".into())), None);
+        assert_eq!(transient_reason(&eyre!("connection error")), None);
+    }
+}
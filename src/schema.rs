@@ -0,0 +1,260 @@
+// ============================
+// Esquema: restricciones e índices
+// ============================
+//
+// Las restricciones e índices que necesitan las consultas se declaran aquí
+// (`REQUIRED`) y se comparan con `SHOW CONSTRAINTS` / `SHOW INDEXES` en la base de
+// datos de cada tenant. La comparación es por forma (tipo + etiqueta + propiedad),
+// no por nombre: un índice equivalente creado a mano cuenta como presente.
+// Nunca se borra nada; lo no declarado sólo se informa.
+
+use std::{fmt, str::FromStr};
+
+use color_eyre::eyre::{eyre, Result};
+use neo4rs::Graph;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    Unique,
+    RangeIndex,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaItem {
+    pub name: &'static str,
+    pub kind: SchemaKind,
+    pub label: &'static str,
+    pub property: &'static str,
+}
+
+/// Esquema requerido por la aplicación.
+pub const REQUIRED: &[SchemaItem] = &[
+    // `MATCH (movie:Movie {title:$title})` asume un título único
+    SchemaItem { name: "movie_title_unique", kind: SchemaKind::Unique, label: "Movie", property: "title" },
+    SchemaItem { name: "person_name_unique", kind: SchemaKind::Unique, label: "Person", property: "name" },
+    // El `MERGE` del voto idempotente sólo es seguro con la clave única
+    SchemaItem {
+        name: "idempotency_key_unique",
+        kind: SchemaKind::Unique,
+        label: "IdempotencyKey",
+        property: "key",
+    },
+    // Caducidad de las claves (`IDEMPOTENCY_KEY_TTL_SECS`)
+    SchemaItem {
+        name: "idempotency_key_created_at",
+        kind: SchemaKind::RangeIndex,
+        label: "IdempotencyKey",
+        property: "created_at",
+    },
+    // Filtros `released_gte` / `released_lte` de `/graph`
+    SchemaItem { name: "movie_released", kind: SchemaKind::RangeIndex, label: "Movie", property: "released" },
+    // Slug de las vistas guardadas (`/views`): un alta repetida es un 409
+    SchemaItem {
+        name: "saved_view_slug_unique",
+        kind: SchemaKind::Unique,
+        label: crate::views::VIEW_LABEL,
+        property: "slug",
+    },
+    MIGRATION_VERSION_UNIQUE,
+    MIGRATION_LOCK_UNIQUE,
+];
+
+/// Contabilidad de `migrations.rs` (las migraciones también las crean si faltan).
+pub const MIGRATION_VERSION_UNIQUE: SchemaItem = SchemaItem {
+    name: "migration_version_unique",
+    kind: SchemaKind::Unique,
+    label: "__Migration",
+    property: "version",
+};
+pub const MIGRATION_LOCK_UNIQUE: SchemaItem = SchemaItem {
+    name: "migration_lock_unique",
+    kind: SchemaKind::Unique,
+    label: "__MigrationLock",
+    property: "id",
+};
+
+impl SchemaItem {
+    pub fn create(&self) -> String {
+        let Self { name, label, property, .. } = self;
+        match self.kind {
+            SchemaKind::Unique => {
+                format!("CREATE CONSTRAINT {name} IF NOT EXISTS FOR (n:{label}) REQUIRE n.{property} IS UNIQUE")
+            }
+            SchemaKind::RangeIndex => format!("CREATE RANGE INDEX {name} IF NOT EXISTS FOR (n:{label}) ON (n.{property})"),
+        }
+    }
+}
+
+impl fmt::Display for SchemaItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            SchemaKind::Unique => "UNIQUE",
+            SchemaKind::RangeIndex => "RANGE INDEX",
+        };
+        write!(f, "{} ({kind} :{}({}))", self.name, self.label, self.property)
+    }
+}
+
+/// Qué hacer con el esquema (al arrancar: `SCHEMA_ON_STARTUP`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaMode {
+    #[default]
+    Off,
+    /// Falla si falta algo
+    Check,
+    /// Crea lo que falte
+    Apply,
+}
+
+impl FromStr for SchemaMode {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" | "" => Ok(Self::Off),
+            "check" => Ok(Self::Check),
+            "apply" => Ok(Self::Apply),
+            other => Err(eyre!("invalid schema mode `{other}` (off|check|apply)")),
+        }
+    }
+}
+
+/// Faltan restricciones o índices y el modo es `check`.
+#[derive(Debug, Clone)]
+pub struct SchemaDrift {
+    pub tenant: String,
+    pub missing: Vec<&'static str>,
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "schema drift in tenant `{}`: missing {}", self.tenant, self.missing.join(", "))
+    }
+}
+
+impl std::error::Error for SchemaDrift {}
+
+/// Resultado de comparar `REQUIRED` con la base de datos.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDiff {
+    pub present: Vec<SchemaItem>,
+    pub missing: Vec<SchemaItem>,
+    /// Restricciones/índices no declarados (nombre); no se tocan
+    pub extra: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_drifted(&self) -> bool {
+        !self.missing.is_empty()
+    }
+}
+
+/// Restricción o índice existente.
+#[derive(Debug)]
+struct Existing {
+    name: String,
+    kind: Option<SchemaKind>,
+    labels: Vec<String>,
+    properties: Vec<String>,
+    /// Índice que respalda una restricción (no se informa como "extra")
+    owned: bool,
+}
+
+impl Existing {
+    fn satisfies(&self, item: &SchemaItem) -> bool {
+        // El índice RANGE que respalda una restricción única también sirve como índice
+        let kind_ok = self.kind == Some(item.kind) || (item.kind == SchemaKind::RangeIndex && self.owned);
+        kind_ok && self.labels == [item.label] && self.properties == [item.property]
+    }
+}
+
+pub async fn diff(db: &Graph) -> Result<SchemaDiff> {
+    let existing = existing(db).await?;
+    let mut diff = SchemaDiff::default();
+
+    for item in REQUIRED {
+        if existing.iter().any(|e| e.satisfies(item)) {
+            diff.present.push(*item);
+        } else {
+            diff.missing.push(*item);
+        }
+    }
+    diff.extra = existing
+        .iter()
+        // Los índices LOOKUP (sin propiedades) son los de fábrica de Neo4j
+        .filter(|e| !e.owned && !e.properties.is_empty())
+        .filter(|e| !REQUIRED.iter().any(|item| e.satisfies(item)))
+        .map(|e| e.name.clone())
+        .collect();
+    Ok(diff)
+}
+
+/// Crea lo que falta (`IF NOT EXISTS`: es seguro repetirlo).
+pub async fn apply(db: &Graph, diff: &SchemaDiff) -> Result<()> {
+    for item in &diff.missing {
+        db.run(neo4rs::query(&item.create())).await?;
+        info!(%item, "schema item created");
+    }
+    Ok(())
+}
+
+/// Paso de arranque para un tenant según `mode`.
+pub async fn sync(db: &Graph, tenant: &str, mode: SchemaMode) -> Result<()> {
+    if mode == SchemaMode::Off {
+        return Ok(());
+    }
+    let diff = diff(db).await?;
+    match mode {
+        SchemaMode::Check if diff.is_drifted() => Err(SchemaDrift {
+            tenant: tenant.to_string(),
+            missing: diff.missing.iter().map(|i| i.name).collect(),
+        }
+        .into()),
+        SchemaMode::Apply => apply(db, &diff).await,
+        _ => Ok(()),
+    }
+}
+
+async fn existing(db: &Graph) -> Result<Vec<Existing>> {
+    const CONSTRAINTS: &str = r#"
+        SHOW CONSTRAINTS YIELD name, type, entityType, labelsOrTypes, properties
+        WHERE entityType = 'NODE'
+        RETURN name, type, labelsOrTypes, properties
+    "#;
+    const INDEXES: &str = r#"
+        SHOW INDEXES YIELD name, type, entityType, labelsOrTypes, properties, owningConstraint
+        WHERE entityType = 'NODE'
+        RETURN name, type, labelsOrTypes, properties, owningConstraint IS NOT NULL AS owned
+    "#;
+
+    let mut existing = Vec::new();
+
+    let mut rows = db.execute(neo4rs::query(CONSTRAINTS)).await?;
+    while let Some(row) = rows.next().await? {
+        let kind: String = row.get("type")?;
+        existing.push(Existing {
+            name: row.get("name")?,
+            // Neo4j 5 usa `UNIQUENESS` o `NODE_PROPERTY_UNIQUENESS` según la versión
+            kind: matches!(kind.as_str(), "UNIQUENESS" | "NODE_PROPERTY_UNIQUENESS" | "NODE_KEY")
+                .then_some(SchemaKind::Unique),
+            labels: row.get::<Option<Vec<String>>>("labelsOrTypes")?.unwrap_or_default(),
+            properties: row.get::<Option<Vec<String>>>("properties")?.unwrap_or_default(),
+            owned: false,
+        });
+    }
+
+    let mut rows = db.execute(neo4rs::query(INDEXES)).await?;
+    while let Some(row) = rows.next().await? {
+        let kind: String = row.get("type")?;
+        existing.push(Existing {
+            name: row.get("name")?,
+            kind: (kind == "RANGE").then_some(SchemaKind::RangeIndex),
+            labels: row.get::<Option<Vec<String>>>("labelsOrTypes")?.unwrap_or_default(),
+            properties: row.get::<Option<Vec<String>>>("properties")?.unwrap_or_default(),
+            owned: row.get("owned")?,
+        });
+    }
+
+    Ok(existing)
+}
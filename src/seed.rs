@@ -0,0 +1,271 @@
+// ============================
+// Seed: dataset Movies embebido
+// ============================
+//
+// `fixtures/movies.json` es el grafo Movies de Neo4j (`:play movies`) embebido en el
+// binario, para no depender del demo público en desarrollo y pruebas. La carga usa
+// `MERGE` por `Movie.title` / `Person.name`, así que repetirla no duplica nada.
+//
+// Las copias sintéticas (`--copies N`) repiten el grafo con títulos y nombres con
+// sufijo ` #k` y la propiedad `synthetic_copy = k`, para pruebas de carga.
+
+use std::collections::HashMap;
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use neo4rs::{BoltType, Graph};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::schema::{self, SchemaMode};
+
+const FIXTURE: &str = include_str!("../fixtures/movies.json");
+
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    people: Vec<FixturePerson>,
+    movies: Vec<FixtureMovie>,
+    /// Pares (seguidor, seguido)
+    follows: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixturePerson {
+    name: String,
+    born: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureMovie {
+    title: String,
+    released: i64,
+    tagline: Option<String>,
+    #[serde(default)]
+    acted_in: Vec<Cast>,
+    #[serde(default)]
+    directed: Vec<String>,
+    #[serde(default)]
+    produced: Vec<String>,
+    #[serde(default)]
+    wrote: Vec<String>,
+    #[serde(default)]
+    reviewed: Vec<Review>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cast {
+    name: String,
+    roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Review {
+    name: String,
+    summary: String,
+    rating: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SeedOptions {
+    /// Borra los datos (salvo la contabilidad de migraciones) antes de cargar
+    pub wipe: bool,
+    /// Copias sintéticas además del original
+    pub copies: u32,
+    /// Filas por transacción
+    pub batch_size: usize,
+}
+
+/// Recuento de la base de datos tras la carga.
+#[derive(Debug, Clone, Copy)]
+pub struct SeedReport {
+    pub movies: i64,
+    pub people: i64,
+    pub relationships: i64,
+}
+
+pub async fn seed(db: &Graph, tenant: &str, opts: SeedOptions) -> Result<SeedReport> {
+    let fixture: Fixture = serde_json::from_str(FIXTURE).wrap_err("invalid bundled fixture")?;
+
+    if opts.wipe {
+        warn!(tenant, "wiping database before seeding");
+        wipe(db).await?;
+    }
+    // Los MERGE/MATCH por título y nombre necesitan las restricciones únicas
+    schema::sync(db, tenant, SchemaMode::Apply).await?;
+
+    let copies = || 0..=opts.copies;
+    let batch = opts.batch_size.max(1);
+
+    let people = copies().flat_map(|k| {
+        fixture.people.iter().map(move |p| {
+            row([("name", named(&p.name, k)), ("born", p.born.into()), ("copy", copy(k))])
+        })
+    });
+    run_batches(db, PEOPLE, people, batch).await?;
+
+    let movies = copies().flat_map(|k| {
+        fixture.movies.iter().map(move |m| {
+            row([
+                ("title", named(&m.title, k)),
+                ("released", m.released.into()),
+                ("tagline", m.tagline.clone().into()),
+                ("copy", copy(k)),
+            ])
+        })
+    });
+    run_batches(db, MOVIES, movies, batch).await?;
+
+    let acted_in = copies().flat_map(|k| {
+        fixture.movies.iter().flat_map(move |m| {
+            m.acted_in.iter().map(move |c| {
+                row([
+                    ("person", named(&c.name, k)),
+                    ("movie", named(&m.title, k)),
+                    ("roles", c.roles.clone().into()),
+                ])
+            })
+        })
+    });
+    run_batches(db, ACTED_IN, acted_in, batch).await?;
+
+    for (query, people_of) in [
+        (DIRECTED, (|m: &FixtureMovie| &m.directed) as fn(&FixtureMovie) -> &Vec<String>),
+        (PRODUCED, |m| &m.produced),
+        (WROTE, |m| &m.wrote),
+    ] {
+        let rows = copies().flat_map(|k| {
+            fixture.movies.iter().flat_map(move |m| {
+                people_of(m)
+                    .iter()
+                    .map(move |name| row([("person", named(name, k)), ("movie", named(&m.title, k))]))
+            })
+        });
+        run_batches(db, query, rows, batch).await?;
+    }
+
+    let reviewed = copies().flat_map(|k| {
+        fixture.movies.iter().flat_map(move |m| {
+            m.reviewed.iter().map(move |r| {
+                row([
+                    ("person", named(&r.name, k)),
+                    ("movie", named(&m.title, k)),
+                    ("summary", r.summary.as_str().into()),
+                    ("rating", r.rating.into()),
+                ])
+            })
+        })
+    });
+    run_batches(db, REVIEWED, reviewed, batch).await?;
+
+    let follows = copies().flat_map(|k| {
+        fixture
+            .follows
+            .iter()
+            .map(move |(from, to)| row([("from", named(from, k)), ("to", named(to, k))]))
+    });
+    run_batches(db, FOLLOWS, follows, batch).await?;
+
+    let report = count(db).await?;
+    info!(tenant, copies = opts.copies, ?report, "seed done");
+    Ok(report)
+}
+
+// `copy` es null en el original: así un re-seed sin copias limpia la marca
+const PEOPLE: &str = r#"
+    UNWIND $rows AS row
+    MERGE (p:Person {name: row.name})
+    SET p.born = row.born, p.synthetic_copy = row.copy
+"#;
+const MOVIES: &str = r#"
+    UNWIND $rows AS row
+    MERGE (m:Movie {title: row.title})
+    ON CREATE SET m.votes = 0
+    SET m.released = row.released, m.tagline = row.tagline, m.synthetic_copy = row.copy
+"#;
+const ACTED_IN: &str = r#"
+    UNWIND $rows AS row
+    MATCH (p:Person {name: row.person})
+    MATCH (m:Movie {title: row.movie})
+    MERGE (p)-[r:ACTED_IN]->(m)
+    SET r.roles = row.roles
+"#;
+const DIRECTED: &str = r#"
+    UNWIND $rows AS row
+    MATCH (p:Person {name: row.person})
+    MATCH (m:Movie {title: row.movie})
+    MERGE (p)-[:DIRECTED]->(m)
+"#;
+const PRODUCED: &str = r#"
+    UNWIND $rows AS row
+    MATCH (p:Person {name: row.person})
+    MATCH (m:Movie {title: row.movie})
+    MERGE (p)-[:PRODUCED]->(m)
+"#;
+const WROTE: &str = r#"
+    UNWIND $rows AS row
+    MATCH (p:Person {name: row.person})
+    MATCH (m:Movie {title: row.movie})
+    MERGE (p)-[:WROTE]->(m)
+"#;
+const REVIEWED: &str = r#"
+    UNWIND $rows AS row
+    MATCH (p:Person {name: row.person})
+    MATCH (m:Movie {title: row.movie})
+    MERGE (p)-[r:REVIEWED]->(m)
+    SET r.summary = row.summary, r.rating = row.rating
+"#;
+const FOLLOWS: &str = r#"
+    UNWIND $rows AS row
+    MATCH (a:Person {name: row.from})
+    MATCH (b:Person {name: row.to})
+    MERGE (a)-[:FOLLOWS]->(b)
+"#;
+
+/// Ejecuta `query` con `$rows` en lotes de `batch` filas (una transacción por lote).
+async fn run_batches(db: &Graph, query: &str, rows: impl Iterator<Item = BoltType>, batch: usize) -> Result<()> {
+    let mut rows = rows.peekable();
+    while rows.peek().is_some() {
+        let chunk: Vec<BoltType> = rows.by_ref().take(batch).collect();
+        db.run(neo4rs::query(query).param("rows", chunk)).await?;
+    }
+    Ok(())
+}
+
+/// Borra todo salvo los nodos internos (`dump::INTERNAL_LABELS`), por lotes.
+async fn wipe(db: &Graph) -> Result<()> {
+    const WIPE: &str = r#"
+        MATCH (n)
+        WHERE none(l IN labels(n) WHERE l IN $internal)
+        CALL { WITH n DETACH DELETE n } IN TRANSACTIONS OF 10000 ROWS
+    "#;
+    db.run(neo4rs::query(WIPE).param("internal", crate::dump::INTERNAL_LABELS.to_vec())).await?;
+    Ok(())
+}
+
+async fn count(db: &Graph) -> Result<SeedReport> {
+    const COUNT: &str = r#"
+        CALL { MATCH (m:Movie) RETURN count(m) AS movies }
+        CALL { MATCH (p:Person) RETURN count(p) AS people }
+        CALL { MATCH ()-[r]->() RETURN count(r) AS relationships }
+        RETURN movies, people, relationships
+    "#;
+    let mut rows = db.execute(neo4rs::query(COUNT)).await?;
+    let row = rows.single().await?;
+    Ok(SeedReport { movies: row.get("movies")?, people: row.get("people")?, relationships: row.get("relationships")? })
+}
+
+fn row<const N: usize>(fields: [(&'static str, BoltType); N]) -> BoltType {
+    HashMap::from(fields).into()
+}
+
+/// Nombre/título de la copia `k` (la 0 es el original).
+fn named(name: &str, k: u32) -> BoltType {
+    if k == 0 {
+        name.into()
+    } else {
+        format!("{name} #{k}").into()
+    }
+}
+
+fn copy(k: u32) -> BoltType {
+    (k > 0).then_some(k as i64).into()
+}
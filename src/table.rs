@@ -0,0 +1,411 @@
+// ============================
+// Exportación tabular (Apache Arrow IPC)
+// ============================
+//
+// Nodos y aristas como tablas Arrow (formato IPC *stream*) para cargarlas en pandas o
+// Polars sin pasar por JSON. Las columnas de propiedades salen de la unión de las
+// claves de propiedades, con el tipo de GraphML/GEXF (`AttrType`: long, double,
+// boolean, string; listas, fechas y puntos como texto). Las propiedades que chocan con
+// las columnas fijas se renombran a `prop_<nombre>`, como en `formats.rs`.
+//
+// - `/graph?format=arrow&table=nodes|edges`: el subgrafo de `/graph`; `id`, `source` y
+//   `target` son los índices del JSON.
+// - `/export/table?table=nodes|edges`: el grafo completo en streaming, por lotes de
+//   `BATCH_ROWS` filas; los ids son los `elementId` de Neo4j.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow_array::{
+    builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use axum::{
+    body::Body,
+    extract::Query,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, VARY},
+    response::{IntoResponse, Response},
+};
+use color_eyre::eyre::Result;
+use neo4rs::{Operation, Txn};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt as _};
+use tokio_util::io::ReaderStream;
+use tracing::{error, info, Instrument as _};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    admin::Admin,
+    bookmarks::Bookmarks,
+    deadline::Cancellable,
+    dump::{INTERNAL_LABELS, NATIVE_TYPES},
+    formats::{self, AttrType},
+    AppError, BrowseResponse, Service,
+};
+
+pub const ARROW_STREAM: &str = "application/vnd.apache.arrow.stream";
+
+/// Filas por `RecordBatch` en `/export/table`.
+const BATCH_ROWS: usize = 8192;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Table {
+    #[default]
+    Nodes,
+    Edges,
+}
+
+impl Table {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Nodes => "nodes",
+            Self::Edges => "edges",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TableQuery {
+    /// Tabla Arrow: nodes (por defecto) o edges
+    #[serde(default)]
+    pub table: Table,
+}
+
+// ============================
+// Columnas
+// ============================
+
+/// Columna en construcción.
+enum Column {
+    Long(Int64Builder),
+    Double(Float64Builder),
+    Boolean(BooleanBuilder),
+    String(StringBuilder),
+}
+
+impl Column {
+    fn new(ty: AttrType) -> Self {
+        match ty {
+            AttrType::Long => Self::Long(Int64Builder::new()),
+            AttrType::Double => Self::Double(Float64Builder::new()),
+            AttrType::Boolean => Self::Boolean(BooleanBuilder::new()),
+            AttrType::String => Self::String(StringBuilder::new()),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Self::Long(_) => DataType::Int64,
+            Self::Double(_) => DataType::Float64,
+            Self::Boolean(_) => DataType::Boolean,
+            Self::String(_) => DataType::Utf8,
+        }
+    }
+
+    /// Añade un valor; si no encaja con el tipo de la columna queda nulo.
+    fn append(&mut self, value: Option<&Value>) {
+        let value = value.filter(|v| !v.is_null());
+        match self {
+            Self::Long(b) => b.append_option(value.and_then(Value::as_i64)),
+            Self::Double(b) => b.append_option(value.and_then(Value::as_f64)),
+            Self::Boolean(b) => b.append_option(value.and_then(Value::as_bool)),
+            Self::String(b) => b.append_option(value.map(formats::text)),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Long(b) => Arc::new(b.finish()),
+            Self::Double(b) => Arc::new(b.finish()),
+            Self::Boolean(b) => Arc::new(b.finish()),
+            Self::String(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Columnas fijas de cada tabla: en `/graph` los ids son índices; en la exportación, `elementId`.
+fn key_columns(table: Table, id: AttrType) -> Vec<(&'static str, AttrType)> {
+    match table {
+        Table::Nodes => vec![("id", id), ("label", AttrType::String), ("kind", AttrType::String)],
+        Table::Edges if id == AttrType::Long => {
+            vec![("source", id), ("target", id), ("rel", AttrType::String)]
+        }
+        Table::Edges => vec![("id", id), ("source", id), ("target", id), ("rel", AttrType::String)],
+    }
+}
+
+/// Tabla en construcción: columnas fijas y una por propiedad (nula donde falta).
+struct TableBuilder {
+    schema: SchemaRef,
+    keys: Vec<Column>,
+    props: Vec<Column>,
+    /// Nombre exportado de la propiedad → posición en `props`
+    index: BTreeMap<String, usize>,
+    rows: usize,
+}
+
+impl TableBuilder {
+    fn new(keys: &[(&str, AttrType)], props: &BTreeMap<String, AttrType>) -> Self {
+        let keys: Vec<(&str, Column)> = keys.iter().map(|(name, ty)| (*name, Column::new(*ty))).collect();
+        let props: Vec<(&str, Column)> = props.iter().map(|(name, ty)| (name.as_str(), Column::new(*ty))).collect();
+        let fields: Vec<Field> = keys
+            .iter()
+            .map(|(name, c)| Field::new(*name, c.data_type(), false))
+            .chain(props.iter().map(|(name, c)| Field::new(*name, c.data_type(), true)))
+            .collect();
+        Self {
+            schema: Arc::new(Schema::new(fields)),
+            index: props.iter().enumerate().map(|(i, (name, _))| (name.to_string(), i)).collect(),
+            keys: keys.into_iter().map(|(_, c)| c).collect(),
+            props: props.into_iter().map(|(_, c)| c).collect(),
+            rows: 0,
+        }
+    }
+
+    /// Una fila: valores de las columnas fijas (en orden) y las propiedades con su nombre original.
+    fn append<'a>(&mut self, keys: &[Value], props: impl IntoIterator<Item = (&'a str, &'a Value)>) {
+        for (column, value) in self.keys.iter_mut().zip(keys) {
+            column.append(Some(value));
+        }
+        let mut values: Vec<Option<&Value>> = vec![None; self.props.len()];
+        for (key, value) in props {
+            if let Some(&i) = self.index.get(&*formats::prop_name(key)) {
+                values[i] = Some(value);
+            }
+        }
+        for (column, value) in self.props.iter_mut().zip(values) {
+            column.append(value);
+        }
+        self.rows += 1;
+    }
+
+    fn finish(&mut self) -> Result<RecordBatch> {
+        let columns = self.keys.iter_mut().chain(&mut self.props).map(Column::finish).collect();
+        self.rows = 0;
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+// ============================
+// /graph?format=arrow
+// ============================
+
+/// Respuesta Arrow del subgrafo de `/graph`.
+pub fn render(graph: &BrowseResponse, table: Table) -> Response {
+    match graph_ipc(graph, table) {
+        Ok(body) => ([(CONTENT_TYPE, ARROW_STREAM), (VARY, "accept")], body).into_response(),
+        Err(e) => AppError::from(e).into_response(),
+    }
+}
+
+fn graph_ipc(graph: &BrowseResponse, table: Table) -> Result<Vec<u8>> {
+    let keys = key_columns(table, AttrType::Long);
+    let mut builder = match table {
+        Table::Nodes => {
+            let mut builder = TableBuilder::new(&keys, &formats::node_columns(graph));
+            for (i, node) in graph.nodes.iter().enumerate() {
+                let keys = [i.into(), node.title.clone().into(), node.label.clone().into()];
+                builder.append(&keys, formats::props(&node.props));
+            }
+            builder
+        }
+        Table::Edges => {
+            let mut builder = TableBuilder::new(&keys, &BTreeMap::new());
+            for link in &graph.links {
+                builder.append(&[link.source.into(), link.target.into(), link.rel.clone().into()], []);
+            }
+            builder
+        }
+    };
+
+    let mut writer = StreamWriter::try_new(Vec::new(), &builder.schema)?;
+    writer.write(&builder.finish()?)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+// ============================
+// /export/table
+// ============================
+
+const NODE_COLUMNS: &str = r#"
+    MATCH (n) WHERE none(l IN labels(n) WHERE l IN $internal)
+    UNWIND keys(n) AS key
+    RETURN key, collect(DISTINCT valueType(n[key])) AS types
+"#;
+
+const EDGE_COLUMNS: &str = r#"
+    MATCH (a)-[r]->(b)
+    WHERE none(l IN labels(a) WHERE l IN $internal) AND none(l IN labels(b) WHERE l IN $internal)
+    UNWIND keys(r) AS key
+    RETURN key, collect(DISTINCT valueType(r[key])) AS types
+"#;
+
+const NODE_ROWS: &str = r#"
+    MATCH (n) WHERE none(l IN labels(n) WHERE l IN $internal)
+    RETURN elementId(n) AS id,
+           CASE WHEN n:Movie THEN 'movie' WHEN n:Person THEN 'person' ELSE 'node' END AS kind,
+           CASE WHEN n:Movie THEN n.title WHEN n:Person THEN n.name END AS title,
+           [k IN keys(n) | [k, CASE WHEN valueType(n[k]) =~ $native THEN n[k] ELSE toString(n[k]) END]] AS props
+"#;
+
+const EDGE_ROWS: &str = r#"
+    MATCH (a)-[r]->(b)
+    WHERE none(l IN labels(a) WHERE l IN $internal) AND none(l IN labels(b) WHERE l IN $internal)
+    RETURN elementId(r) AS id, elementId(a) AS source, elementId(b) AS target, type(r) AS rel,
+           [k IN keys(r) | [k, CASE WHEN valueType(r[k]) =~ $native THEN r[k] ELSE toString(r[k]) END]] AS props
+"#;
+
+/// Columnas de propiedades de la tabla (nombre exportado → tipo común de sus valores).
+async fn columns(
+    service: &Service,
+    txn: &mut Txn,
+    cancel: &Cancellable,
+    table: Table,
+) -> Result<BTreeMap<String, AttrType>> {
+    let cypher = match table {
+        Table::Nodes => NODE_COLUMNS,
+        Table::Edges => EDGE_COLUMNS,
+    };
+    let q = cancel.query(cypher).param("internal", INTERNAL_LABELS.to_vec());
+    let mut rows = service.execute(txn, "export_table_columns", q).await?;
+    let mut columns: BTreeMap<String, AttrType> = BTreeMap::new();
+    while let Some(row) = rows.next(txn).await? {
+        let key: String = row.get("key")?;
+        let types: Vec<String> = row.get("types")?;
+        let ty = types.iter().map(|t| AttrType::of_value_type(t)).reduce(AttrType::merge).unwrap_or(AttrType::String);
+        columns.entry(formats::prop_name(&key).into_owned()).and_modify(|t| *t = t.merge(ty)).or_insert(ty);
+    }
+    Ok(columns)
+}
+
+/// Escribe la tabla completa en `out` como stream IPC y lo cierra. `txn` es la misma
+/// transacción de lectura de `columns`: las filas salen de la misma instantánea.
+async fn export_rows<W>(
+    service: &Service,
+    mut txn: Txn,
+    cancel: &Cancellable,
+    table: Table,
+    columns: &BTreeMap<String, AttrType>,
+    mut out: W,
+) -> Result<u64>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let mut builder = TableBuilder::new(&key_columns(table, AttrType::String), columns);
+    // El esquema sale en cuanto se crea el writer; los lotes se pasan a `out` según se cierran
+    let mut writer = StreamWriter::try_new(Vec::new(), &builder.schema)?;
+    flush(&mut writer, &mut out).await?;
+
+    let cypher = match table {
+        Table::Nodes => NODE_ROWS,
+        Table::Edges => EDGE_ROWS,
+    };
+    let q = cancel.query(cypher).param("internal", INTERNAL_LABELS.to_vec()).param("native", NATIVE_TYPES);
+    let mut rows = service.execute(&mut txn, "export_table", q).await?;
+    let mut total = 0;
+    while let Some(row) = rows.next(&mut txn).await? {
+        let id: String = row.get("id")?;
+        let props: Vec<Vec<Value>> = row.get("props")?;
+        let props = props.iter().filter_map(|pair| match pair.as_slice() {
+            [Value::String(key), value] => Some((key.as_str(), value)),
+            _ => None,
+        });
+        let keys: Vec<Value> = match table {
+            Table::Nodes => {
+                let title: Option<String> = row.get("title")?;
+                let title = title.unwrap_or_else(|| format!("#{id}"));
+                vec![id.into(), title.into(), row.get::<String>("kind")?.into()]
+            }
+            Table::Edges => vec![
+                id.into(),
+                row.get::<String>("source")?.into(),
+                row.get::<String>("target")?.into(),
+                row.get::<String>("rel")?.into(),
+            ],
+        };
+        builder.append(&keys, props);
+        total += 1;
+
+        if builder.rows >= BATCH_ROWS {
+            writer.write(&builder.finish()?)?;
+            flush(&mut writer, &mut out).await?;
+        }
+    }
+    txn.commit().await?;
+    if builder.rows > 0 {
+        writer.write(&builder.finish()?)?;
+    }
+    // Marca de fin de stream: sin ella, la tabla está truncada
+    writer.finish()?;
+    flush(&mut writer, &mut out).await?;
+    out.shutdown().await?;
+    Ok(total)
+}
+
+async fn flush<W: AsyncWrite + Unpin>(writer: &mut StreamWriter<Vec<u8>>, out: &mut W) -> Result<()> {
+    let bytes = std::mem::take(writer.get_mut());
+    out.write_all(&bytes).await?;
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/export/table",
+    tag = "movies",
+    params(
+        TableQuery,
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 200, description = "Grafo completo como tabla Arrow (IPC stream, en streaming por lotes; sin la marca de fin está truncado)",
+            content(("application/vnd.apache.arrow.stream" = String))),
+        (status = 401, description = "Token de administración inválido"),
+        (status = 403, description = "API de administración deshabilitada"),
+        (status = 503, description = "Neo4j no disponible o pool agotado"),
+        (status = 504, description = "Timeout al inferir las columnas")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn export(
+    _admin: Admin,
+    service: Service,
+    bookmarks: Bookmarks,
+    Query(query): Query<TableQuery>,
+) -> Result<Response, AppError> {
+    let table = query.table;
+
+    // Las columnas se infieren antes del primer byte: aquí un fallo aún es un error HTTP.
+    // La transacción sigue abierta para las filas (misma instantánea que las columnas).
+    let cancel = Cancellable::new();
+    let (txn, columns) = service
+        .guarded_read("export_table_columns", service.timeouts.graph, &cancel, || async {
+            let mut txn = service.read_txn(&bookmarks).await?;
+            let columns = columns(&service, &mut txn, &cancel, table).await?;
+            Ok((txn, columns))
+        })
+        .await?;
+
+    let headers = [
+        (CONTENT_TYPE, ARROW_STREAM.to_string()),
+        (CONTENT_DISPOSITION, format!("attachment; filename=\"{}-{}.arrows\"", service.tenant, table.as_str())),
+    ];
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let rows = export_rows(&service, txn, &cancel, table, &columns, writer);
+        let res = service
+            .pool
+            .run(cancel.run(&service.db, &service.tenant, "export_table", Operation::Read, service.timeouts.stream, rows))
+            .await;
+        match res {
+            Ok(rows) => info!(tenant = %service.tenant, table = table.as_str(), rows, "table exported"),
+            Err(e) => error!(tenant = %service.tenant, error=?e, "table export aborted; the Arrow stream is truncated"),
+        }
+    }.in_current_span());
+
+    Ok((headers, Body::from_stream(ReaderStream::new(reader))).into_response())
+}
@@ -0,0 +1,103 @@
+// ============================
+// Trazas distribuidas (OpenTelemetry / OTLP)
+// ============================
+//
+// Con `OTEL_EXPORTER_OTLP_ENDPOINT` definido, los spans de `tracing` se exportan por
+// OTLP (gRPC) además de ir al log:
+//   * el span `request` de cada petición continúa la traza del `traceparent` (W3C)
+//     entrante y lleva el `x-request-id` como atributo `request_id`;
+//   * cada Cypher de `Service` es un span hijo `neo4j.query` (`db.system`,
+//     `db.statement`, …; ver `querylog.rs`).
+// Sin endpoint no se instala nada: el `traceparent` se ignora y los spans sólo van al log.
+
+use axum::http::{HeaderMap, Request};
+use color_eyre::eyre::Result;
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    trace::TracerProvider as _,
+};
+use opentelemetry_otlp::WithExportConfig as _;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider, Tracer},
+    Resource,
+};
+use tracing::{warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+#[derive(Clone, Debug)]
+pub struct OtlpConfig {
+    /// Colector OTLP/gRPC (p. ej. `http://otel-collector:4317`)
+    pub endpoint: String,
+    pub service_name: String,
+    /// Fracción de trazas nuevas que se muestrean (0.0–1.0); si la petición trae
+    /// `traceparent`, manda la decisión del llamante
+    pub sampling_ratio: f64,
+}
+
+/// Proveedor de trazas instalado; al soltarse vacía los spans pendientes.
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Se llama dentro del runtime de tokio: el canal gRPC del exportador lo necesita.
+    pub fn install(cfg: OtlpConfig) -> Result<Self> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(cfg.endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(cfg.sampling_ratio.clamp(0.0, 1.0)))))
+            .with_resource(Resource::builder().with_service_name(cfg.service_name).build())
+            .build();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(Self { provider })
+    }
+
+    pub fn tracer(&self) -> Tracer {
+        self.provider.tracer(env!("CARGO_PKG_NAME"))
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            warn!(error = %e, "failed to flush pending spans");
+        }
+    }
+}
+
+/// Span raíz de cada petición HTTP/gRPC (sustituye a `DefaultMakeSpan`).
+///
+/// `x-request-id` ya está puesto: `SetRequestIdLayer` va por fuera del `TraceLayer`.
+pub fn make_span<B>(req: &Request<B>) -> Span {
+    let request_id = req.headers().get("x-request-id").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        version = ?req.version(),
+        request_id,
+        route = tracing::field::Empty,
+        otel.kind = "server",
+    );
+    // Sin propagador instalado (OTLP desactivado) el contexto extraído está vacío
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&Headers(req.headers())));
+    span.set_parent(parent);
+    span
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
@@ -0,0 +1,185 @@
+// ============================
+// Multi-tenant (un catálogo = una base de datos Neo4j)
+// ============================
+//
+// Cada petición se resuelve a un tenant y éste a su base de datos, por prioridad:
+//   1. prefijo de ruta `/t/<tenant>/...` (se elimina antes del enrutado)
+//   2. cabecera `x-tenant`
+//   3. subdominio del host (`series.example.com`) si coincide con un tenant
+//   4. `DEFAULT_TENANT`
+//
+// Cada tenant tiene su propio `Service` (conexión, pool, breaker) y las métricas de
+// Neo4j llevan la etiqueta `tenant`. Los handlers reciben el `Service` ya resuelto.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, StatusCode, Uri},
+};
+use color_eyre::eyre::{bail, eyre, Result};
+
+use crate::{admin::AdminConfig, graphql::MoviesSchema, health::Readiness, AppConfig, AppError, Service};
+
+pub const TENANT_HEADER: &str = "x-tenant";
+const PATH_PREFIX: &str = "/t/";
+
+/// Conexión de un tenant. Lo no indicado en `TENANT_<NOMBRE>_*` se hereda de `NEO4J_*`.
+#[derive(Debug, Clone)]
+pub struct TenantConfig {
+    pub name: String,
+    pub database: String,
+    pub uri: String,
+    pub user: String,
+    pub password: String,
+    pub max_connections: usize,
+    pub fetch_size: usize,
+}
+
+/// Lee `TENANTS` (`nombre=base_de_datos,...`). Sin `TENANTS` hay un único tenant
+/// con el nombre de `NEO4J_DATABASE`.
+pub fn configs(cfg: &AppConfig) -> Result<Vec<TenantConfig>> {
+    let spec = cfg.tenants.clone().unwrap_or_else(|| cfg.neo4j_database.clone());
+    let mut tenants: Vec<TenantConfig> = Vec::new();
+
+    for entry in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (name, database) = entry.split_once('=').unwrap_or((entry, entry));
+        let (name, database) = (name.trim().to_ascii_lowercase(), database.trim().to_string());
+        if !valid_name(&name) || database.is_empty() {
+            bail!("invalid TENANTS entry `{entry}`");
+        }
+        if tenants.iter().any(|t| t.name == name) {
+            bail!("duplicate tenant `{name}` in TENANTS");
+        }
+
+        let prefix = format!("TENANT_{}_", name.to_ascii_uppercase().replace('-', "_"));
+        let env = |key: &str| std::env::var(format!("{prefix}{key}")).ok();
+        tenants.push(TenantConfig {
+            uri: env("NEO4J_URI").unwrap_or_else(|| cfg.neo4j_uri.clone()),
+            user: env("NEO4J_USER").unwrap_or_else(|| cfg.neo4j_user.clone()),
+            password: env("NEO4J_PASSWORD").unwrap_or_else(|| cfg.neo4j_password.clone()),
+            max_connections: env("NEO4J_MAX_CONNECTIONS").and_then(|s| s.parse().ok()).unwrap_or(cfg.neo4j_max_connections),
+            fetch_size: env("NEO4J_FETCH_SIZE").and_then(|s| s.parse().ok()).unwrap_or(cfg.neo4j_fetch_size),
+            name,
+            database,
+        });
+    }
+
+    if tenants.is_empty() {
+        bail!("TENANTS must declare at least one tenant");
+    }
+    Ok(tenants)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 63 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Servicios por tenant + estado compartido del proceso. Es el estado del router.
+#[derive(Clone)]
+pub struct Tenants {
+    services: Arc<BTreeMap<String, Service>>,
+    default: Arc<str>,
+    pub readiness: Arc<Readiness>,
+    pub readiness_timeout: Duration,
+    pub admin: AdminConfig,
+    pub graphql: MoviesSchema,
+}
+
+impl Tenants {
+    pub fn new(
+        services: Vec<Service>,
+        default: &str,
+        readiness: Arc<Readiness>,
+        readiness_timeout: Duration,
+        admin: AdminConfig,
+        graphql: MoviesSchema,
+    ) -> Result<Self> {
+        let services: BTreeMap<String, Service> =
+            services.into_iter().map(|s| (s.tenant.to_string(), s)).collect();
+        let default = default.to_ascii_lowercase();
+        if !services.contains_key(&default) {
+            bail!("DEFAULT_TENANT `{default}` is not declared in TENANTS");
+        }
+        Ok(Self { services: Arc::new(services), default: default.into(), readiness, readiness_timeout, admin, graphql })
+    }
+
+    /// Servicios de todos los tenants (ordenados por nombre).
+    pub fn services(&self) -> impl Iterator<Item = &Service> {
+        self.services.values()
+    }
+
+    fn resolve(&self, parts: &Parts) -> Result<&Service, AppError> {
+        if let Some(PathTenant(name)) = parts.extensions.get::<PathTenant>() {
+            return self.named(name);
+        }
+        if let Some(value) = parts.headers.get(TENANT_HEADER) {
+            let name = value.to_str().map(str::trim).unwrap_or_default().to_ascii_lowercase();
+            return self.named(&name);
+        }
+        // Un subdominio que no es tenant (`www`, `api`…) no es un error
+        if let Some(service) = subdomain(parts).and_then(|name| self.services.get(&name)) {
+            return Ok(service);
+        }
+        Ok(&self.services[&*self.default])
+    }
+
+    fn named(&self, name: &str) -> Result<&Service, AppError> {
+        self.services
+            .get(name)
+            .ok_or_else(|| AppError::new(eyre!("unknown tenant `{name}`"), StatusCode::NOT_FOUND))
+    }
+}
+
+/// Los handlers piden directamente un `Service`: el del tenant de la petición.
+#[async_trait]
+impl FromRequestParts<Tenants> for Service {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, tenants: &Tenants) -> Result<Self, Self::Rejection> {
+        let service = tenants.resolve(parts)?.clone();
+        metrics::counter!("tenant_requests_total", "tenant" => service.tenant.to_string()).increment(1);
+        Ok(service)
+    }
+}
+
+/// Tenant indicado en el prefijo de la ruta (lo deja `strip_path_prefix`).
+#[derive(Debug, Clone)]
+struct PathTenant(String);
+
+/// Reescribe `/t/<tenant>/resto` a `/resto` antes del enrutado y guarda el tenant
+/// en las extensiones de la petición. Se aplica envolviendo el `Router`.
+pub async fn strip_path_prefix(mut req: Request) -> Request {
+    let Some(rest) = req.uri().path().strip_prefix(PATH_PREFIX) else { return req };
+    let (name, tail) = match rest.split_once('/') {
+        Some((name, tail)) => (name.to_ascii_lowercase(), format!("/{tail}")),
+        None => (rest.to_ascii_lowercase(), "/".to_string()),
+    };
+    if name.is_empty() {
+        return req;
+    }
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{tail}?{query}"),
+        None => tail,
+    };
+
+    let mut uri = req.uri().clone().into_parts();
+    uri.path_and_query = path_and_query.parse().ok();
+    if let Ok(uri) = Uri::from_parts(uri) {
+        *req.uri_mut() = uri;
+        req.extensions_mut().insert(PathTenant(name));
+    }
+    req
+}
+
+/// Primera etiqueta del host (sin puerto), si el host tiene al menos un punto.
+fn subdomain(parts: &Parts) -> Option<String> {
+    let host = parts
+        .uri
+        .host()
+        .or_else(|| parts.headers.get(header::HOST).and_then(|h| h.to_str().ok()))?;
+    let host = host.split(':').next().unwrap_or_default();
+    let (label, _) = host.split_once('.')?;
+    Some(label.to_ascii_lowercase())
+}
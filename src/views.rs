@@ -0,0 +1,473 @@
+// ============================
+// Vistas guardadas (/views)
+// ============================
+//
+// Un conjunto de filtros de `/graph` (`Browse`) o una consulta de la consola
+// (`POST /query`) guardados con un nombre y un slug para compartirlos por enlace:
+// `GET /graph?view=<slug>` aplica los filtros de la vista (los parámetros explícitos
+// de la URL tienen prioridad). Se guardan por tenant como nodos `:__SavedView`
+// (internos: no salen en los volcados ni en las exportaciones).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::{extract::Path, http::StatusCode};
+use color_eyre::eyre::{eyre, Result};
+use neo4rs::{Operation, Row};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    bookmarks::{BookmarkResponse, Bookmarks},
+    codec::{Accepts, Decoded, Encoded},
+    deadline::Cancellable,
+    AppError, Browse, Service,
+};
+
+pub const VIEW_LABEL: &str = "__SavedView";
+const MAX_SLUG_LEN: usize = 64;
+const MAX_NAME_LEN: usize = 200;
+
+/// Consulta de la consola guardada (se ejecuta con `POST /query`).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SavedQuery {
+    query: String,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Alta o modificación: `browse` o `query` (exactamente uno).
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ViewInput {
+    name: String,
+    /// `[a-z0-9-]`, hasta 64; por defecto se deriva del nombre (sólo en el alta)
+    #[serde(default)]
+    slug: Option<String>,
+    #[serde(default)]
+    browse: Option<Browse>,
+    #[serde(default)]
+    query: Option<SavedQuery>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SavedView {
+    slug: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    browse: Option<Browse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<SavedQuery>,
+    /// Milisegundos desde epoch
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// `?view=<slug>` de `/graph`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ViewQuery {
+    /// Vista guardada cuyos filtros se aplican (los parámetros explícitos tienen prioridad)
+    #[serde(default)]
+    pub view: Option<String>,
+}
+
+// ============================
+// Handlers
+// ============================
+
+#[utoipa::path(
+    get,
+    path = "/views",
+    tag = "views",
+    params(("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")),
+    responses((status = 200, description = "Vistas guardadas del tenant, por nombre", body = [SavedView]))
+)]
+pub async fn list(Accepts(codec): Accepts, service: Service, bookmarks: Bookmarks) -> Result<Encoded<Vec<SavedView>>, AppError> {
+    Ok(Encoded(codec, service.views(&bookmarks).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/views/{slug}",
+    tag = "views",
+    params(
+        ("slug" = String, Path, description = "Slug de la vista"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 200, description = "Vista guardada", body = SavedView),
+        (status = 404, description = "Vista inexistente")
+    )
+)]
+pub async fn load(
+    Path(slug): Path<String>,
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<Encoded<SavedView>, AppError> {
+    Ok(Encoded(codec, service.view(&slug, &bookmarks).await?.ok_or_else(not_found)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/views",
+    tag = "views",
+    request_body(content = ViewInput, description = "Nombre, slug opcional y `browse` (filtros de `/graph`) o `query` (consola)"),
+    params(("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")),
+    responses(
+        (status = 201, description = "Vista creada", body = SavedView,
+            headers(("x-neo4j-bookmark" = String, description = "Bookmark de la escritura (también en la cookie `neo4j_bookmark`)"))),
+        (status = 400, description = "Nombre, slug o contenido inválido"),
+        (status = 409, description = "Ya existe una vista con ese slug")
+    )
+)]
+pub async fn create(
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+    Decoded(input): Decoded<ViewInput>,
+) -> Result<(StatusCode, BookmarkResponse, Encoded<SavedView>), AppError> {
+    let slug = match input.slug.as_deref() {
+        Some(slug) => slug.trim().to_string(),
+        None => slugify(&input.name),
+    };
+    let fields = Fields::validate(input, &slug)?;
+    let (view, bookmark) = service.save_view(&slug, fields, true, &bookmarks).await.map_err(conflict)?;
+    Ok((StatusCode::CREATED, BookmarkResponse(bookmark), Encoded(codec, view.ok_or_else(not_found)?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/views/{slug}",
+    tag = "views",
+    request_body(content = ViewInput, description = "Nuevo nombre y contenido (el slug no cambia)"),
+    params(
+        ("slug" = String, Path, description = "Slug de la vista"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 200, description = "Vista actualizada", body = SavedView),
+        (status = 400, description = "Nombre o contenido inválido"),
+        (status = 404, description = "Vista inexistente")
+    )
+)]
+pub async fn update(
+    Path(slug): Path<String>,
+    Accepts(codec): Accepts,
+    service: Service,
+    bookmarks: Bookmarks,
+    Decoded(input): Decoded<ViewInput>,
+) -> Result<(BookmarkResponse, Encoded<SavedView>), AppError> {
+    let fields = Fields::validate(input, &slug)?;
+    let (view, bookmark) = service.save_view(&slug, fields, false, &bookmarks).await?;
+    Ok((BookmarkResponse(bookmark), Encoded(codec, view.ok_or_else(not_found)?)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/views/{slug}",
+    tag = "views",
+    params(
+        ("slug" = String, Path, description = "Slug de la vista"),
+        ("x-tenant" = Option<String>, Header, description = "Tenant (catálogo); también por subdominio o prefijo `/t/<tenant>`")
+    ),
+    responses(
+        (status = 204, description = "Vista borrada"),
+        (status = 404, description = "Vista inexistente")
+    )
+)]
+pub async fn delete(
+    Path(slug): Path<String>,
+    service: Service,
+    bookmarks: Bookmarks,
+) -> Result<(StatusCode, BookmarkResponse, ()), AppError> {
+    let (deleted, bookmark) = service.delete_view(&slug, &bookmarks).await?;
+    if !deleted {
+        return Err(not_found());
+    }
+    Ok((StatusCode::NO_CONTENT, BookmarkResponse(bookmark), ()))
+}
+
+/// Filtros de `/graph` con los de la vista `slug` debajo de los explícitos.
+pub async fn apply(service: &Service, slug: &str, explicit: Browse, bookmarks: &Bookmarks) -> Result<Browse, AppError> {
+    let view = service.view(slug, bookmarks).await?.ok_or_else(not_found)?;
+    let Some(saved) = view.browse else {
+        return Err(AppError::new(eyre!("view `{slug}` is a console query (run it with POST /query)"), StatusCode::BAD_REQUEST));
+    };
+    Ok(Browse {
+        limit: explicit.limit.or(saved.limit),
+        rel: explicit.rel.or(saved.rel),
+        root: explicit.root.or(saved.root),
+        depth: explicit.depth.or(saved.depth),
+        node_incl: explicit.node_incl.or(saved.node_incl),
+        node_excl: explicit.node_excl.or(saved.node_excl),
+        released_gte: explicit.released_gte.or(saved.released_gte),
+        released_lte: explicit.released_lte.or(saved.released_lte),
+    })
+}
+
+fn not_found() -> AppError {
+    AppError::new(eyre!("view not found"), StatusCode::NOT_FOUND)
+}
+
+/// Slug repetido en el alta (restricción única `saved_view_slug_unique`).
+fn conflict(err: color_eyre::eyre::Report) -> AppError {
+    let duplicate = matches!(
+        err.downcast_ref::<neo4rs::Error>(),
+        Some(neo4rs::Error::Neo4j(e)) if e.code() == "Neo.ClientError.Schema.ConstraintValidationFailed"
+    );
+    if duplicate {
+        AppError::new(err, StatusCode::CONFLICT)
+    } else {
+        AppError::from(err)
+    }
+}
+
+/// Minúsculas ASCII, dígitos y guiones (sin guiones repetidos ni en los extremos).
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_SLUG_LEN);
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Propiedades del nodo, ya validadas.
+struct Fields {
+    name: String,
+    browse: Option<String>,
+    query: Option<String>,
+}
+
+impl Fields {
+    fn validate(input: ViewInput, slug: &str) -> Result<Self, AppError> {
+        let bad_request = |msg: &str| AppError::new(eyre!("{msg}"), StatusCode::BAD_REQUEST);
+        let valid_slug = !slug.is_empty()
+            && slug.len() <= MAX_SLUG_LEN
+            && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_slug {
+            return Err(bad_request("invalid slug (a-z, 0-9 and '-', up to 64)"));
+        }
+        let name = input.name.trim().to_string();
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(bad_request("invalid name"));
+        }
+        match (&input.browse, &input.query) {
+            (Some(browse), None) => Ok(Self { name, browse: Some(json(browse)?), query: None }),
+            (None, Some(query)) if !query.query.trim().is_empty() => {
+                Ok(Self { name, browse: None, query: Some(json(query)?) })
+            }
+            _ => Err(bad_request("a view needs either `browse` or a non-empty `query`")),
+        }
+    }
+}
+
+/// `Browse` y `SavedQuery` se guardan como texto JSON en el nodo.
+fn json<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|e| AppError::new(e.into(), StatusCode::BAD_REQUEST))
+}
+
+// ============================
+// Service
+// ============================
+
+const VIEW_RETURN: &str = "RETURN v.slug AS slug, v.name AS name, v.browse AS browse, v.query AS query, \
+                           v.created_at AS created_at, v.updated_at AS updated_at";
+
+fn saved_view(row: &Row) -> Result<SavedView> {
+    let browse: Option<String> = row.get("browse")?;
+    let query: Option<String> = row.get("query")?;
+    Ok(SavedView {
+        slug: row.get("slug")?,
+        name: row.get("name")?,
+        browse: browse.map(|b| serde_json::from_str(&b)).transpose()?,
+        query: query.map(|q| serde_json::from_str(&q)).transpose()?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+impl Service {
+    async fn views(&self, bookmarks: &Bookmarks) -> Result<Vec<SavedView>> {
+        let cancel = Cancellable::new();
+        let q = cancel.query(&format!("MATCH (v:{VIEW_LABEL}) {VIEW_RETURN} ORDER BY name, slug"));
+        self.read_views("views", q, &cancel, bookmarks).await
+    }
+
+    async fn view(&self, slug: &str, bookmarks: &Bookmarks) -> Result<Option<SavedView>> {
+        let cancel = Cancellable::new();
+        let q = cancel.query(&format!("MATCH (v:{VIEW_LABEL} {{slug: $slug}}) {VIEW_RETURN}")).param("slug", slug);
+        Ok(self.read_views("view", q, &cancel, bookmarks).await?.pop())
+    }
+
+    async fn read_views(
+        &self,
+        operation: &'static str,
+        q: neo4rs::Query,
+        cancel: &Cancellable,
+        bookmarks: &Bookmarks,
+    ) -> Result<Vec<SavedView>> {
+        self.guarded_read(operation, self.timeouts.default, cancel, || async {
+            let mut txn = self.read_txn(bookmarks).await?;
+            let mut rows = self.execute(&mut txn, operation, q.clone()).await?;
+            let mut views = Vec::new();
+            while let Some(row) = rows.next(&mut txn).await? {
+                views.push(saved_view(&row)?);
+            }
+            txn.commit().await?;
+            Ok(views)
+        })
+        .await
+    }
+
+    /// Alta (`create`) o modificación de una vista existente; `None` si no existe.
+    async fn save_view(
+        &self,
+        slug: &str,
+        fields: Fields,
+        create: bool,
+        bookmarks: &Bookmarks,
+    ) -> Result<(Option<SavedView>, Option<String>)> {
+        let cypher = if create {
+            format!(
+                "CREATE (v:{VIEW_LABEL} {{slug: $slug, created_at: timestamp()}}) \
+                 SET v.name = $name, v.browse = $browse, v.query = $query, v.updated_at = timestamp() {VIEW_RETURN}"
+            )
+        } else {
+            format!(
+                "MATCH (v:{VIEW_LABEL} {{slug: $slug}}) \
+                 SET v.name = $name, v.browse = $browse, v.query = $query, v.updated_at = timestamp() {VIEW_RETURN}"
+            )
+        };
+        let cancel = Cancellable::new();
+        let q = cancel
+            .query(&cypher)
+            .param("slug", slug)
+            .param("name", fields.name)
+            .param("browse", fields.browse)
+            .param("query", fields.query);
+        // Sin clave de idempotencia: un alta repetida sería un 409
+        self.guarded_write("save_view", None, &cancel, || async {
+            let mut txn = self.db.start_txn_as(Operation::Write, bookmarks.to_option()).await?;
+            let mut rows = self.execute(&mut txn, "save_view", q.clone()).await?;
+            let view = match rows.next(&mut txn).await? {
+                Some(row) => Some(saved_view(&row)?),
+                None => None,
+            };
+            let bookmark = txn.commit().await?;
+            Ok((view, bookmark))
+        })
+        .await
+    }
+
+    async fn delete_view(&self, slug: &str, bookmarks: &Bookmarks) -> Result<(bool, Option<String>)> {
+        let cypher = format!("MATCH (v:{VIEW_LABEL} {{slug: $slug}}) DELETE v RETURN count(*) AS deleted");
+        let cancel = Cancellable::new();
+        let q = cancel.query(&cypher).param("slug", slug);
+        let seen = Seen::default();
+        self.guarded_write("delete_view", Some(slug), &cancel, || {
+            let (q, seen) = (q.clone(), &seen);
+            async move {
+                let mut txn = self.db.start_txn_as(Operation::Write, bookmarks.to_option()).await?;
+                let mut rows = self.execute(&mut txn, "delete_view", q).await?;
+                let found = seen.matched(rows.single(&mut txn).await?.get("deleted")?);
+                let bookmark = txn.commit().await?;
+                Ok((found, bookmark))
+            }
+        })
+        .await
+    }
+}
+
+/// Si algún intento de `delete_view` llegó a encontrar la vista. Un reintento que ya no
+/// la encuentra sólo cuenta como borrado si un intento anterior la borró (y se perdió la
+/// confirmación); si nunca existió, sigue siendo 404.
+#[derive(Default)]
+struct Seen(AtomicBool);
+
+impl Seen {
+    /// Anota las filas borradas por un intento (antes de confirmar) y dice si la vista existía.
+    fn matched(&self, deleted: i64) -> bool {
+        if deleted > 0 {
+            self.0.store(true, Ordering::Relaxed);
+        }
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn input(value: serde_json::Value) -> ViewInput {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn valid(value: serde_json::Value, slug: &str) -> Fields {
+        match Fields::validate(input(value), slug) {
+            Ok(fields) => fields,
+            Err(e) => panic!("rejected: {:?}", e.inner),
+        }
+    }
+
+    fn rejected(value: serde_json::Value, slug: &str) -> bool {
+        matches!(Fields::validate(input(value), slug), Err(e) if e.status == StatusCode::BAD_REQUEST)
+    }
+
+    #[test]
+    fn slugify_names() {
+        assert_eq!(slugify("  Matrix: Keanu's Films  "), "matrix-keanu-s-films");
+        assert_eq!(slugify("Ñandú 2024"), "and-2024");
+        assert_eq!(slugify("--__--"), "");
+        assert_eq!(slugify(&"a b ".repeat(40)).len(), MAX_SLUG_LEN - 1);
+        assert!(!slugify(&"a b ".repeat(40)).ends_with('-'));
+    }
+
+    #[test]
+    fn validate_accepts_browse_or_query() {
+        let fields = valid(json!({"name": " Matrix ", "browse": {"root": "The Matrix"}}), "matrix");
+        assert_eq!(fields.name, "Matrix");
+        assert!(fields.browse.unwrap().contains("The Matrix"));
+        assert!(fields.query.is_none());
+
+        let fields = valid(json!({"name": "q", "query": {"query": "RETURN $x", "params": {"x": 1}}}), "q-1");
+        assert!(fields.browse.is_none());
+        assert_eq!(fields.query.unwrap(), r#"{"query":"RETURN $x","params":{"x":1}}"#);
+    }
+
+    #[test]
+    fn validate_rejects_bad_input() {
+        let browse = json!({"name": "n", "browse": {}});
+        assert!(rejected(browse.clone(), ""));
+        assert!(rejected(browse.clone(), "Upper"));
+        assert!(rejected(browse.clone(), "with space"));
+        assert!(rejected(browse.clone(), &"a".repeat(MAX_SLUG_LEN + 1)));
+        assert!(!rejected(browse, &"a".repeat(MAX_SLUG_LEN)));
+
+        assert!(rejected(json!({"name": "  ", "browse": {}}), "ok"));
+        assert!(rejected(json!({"name": "x".repeat(MAX_NAME_LEN + 1), "browse": {}}), "ok"));
+        assert!(rejected(json!({"name": "n"}), "ok"));
+        assert!(rejected(json!({"name": "n", "query": {"query": "  "}}), "ok"));
+        assert!(rejected(json!({"name": "n", "browse": {}, "query": {"query": "RETURN 1"}}), "ok"));
+    }
+
+    #[test]
+    fn delete_retry_counts_only_a_view_seen_before() {
+        // 1er intento: no había vista y falló antes de confirmar; el reintento sigue siendo 404
+        let seen = Seen::default();
+        assert!(!seen.matched(0));
+        assert!(!seen.matched(0));
+
+        // 1er intento: la borró pero se perdió la confirmación; el reintento ya no la ve
+        let seen = Seen::default();
+        assert!(seen.matched(1));
+        assert!(seen.matched(0));
+    }
+}